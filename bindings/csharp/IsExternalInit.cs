@@ -0,0 +1,10 @@
+#if !NET5_0_OR_GREATER
+namespace System.Runtime.CompilerServices;
+
+/// <summary>
+/// Allows using <c>init</c> accessors and records when targeting frameworks older than .NET 5.
+/// </summary>
+internal static class IsExternalInit
+{
+}
+#endif
@@ -0,0 +1,23 @@
+using Newtonsoft.Json;
+
+namespace NeatProto.Newtonsoft;
+
+/// <summary>
+/// Converter which makes Newtonsoft.Json fall back to its default (de)serialization. Cases of tagged unions
+/// are annotated with it, because otherwise they would inherit the converter of the abstract class representing
+/// the union.
+/// </summary>
+public sealed class DefaultJsonConverter : JsonConverter
+{
+    public override bool CanRead => false;
+
+    public override bool CanWrite => false;
+
+    public override bool CanConvert(Type objectType) => false;
+
+    public override object? ReadJson(JsonReader reader, Type objectType, object? existingValue, JsonSerializer serializer)
+        => throw new NotSupportedException();
+
+    public override void WriteJson(JsonWriter writer, object? value, JsonSerializer serializer)
+        => throw new NotSupportedException();
+}
@@ -0,0 +1,33 @@
+using System.Globalization;
+using Newtonsoft.Json;
+
+namespace NeatProto.Newtonsoft;
+
+/// <summary>
+/// Reads and writes values wrapped by aliases. Newtonsoft.Json does not support 128-bit integers, so they are
+/// written as raw numbers, everything else is (de)serialized using the serializer.
+/// </summary>
+public static class JsonValues
+{
+    public static void Write<T>(JsonWriter writer, T value, JsonSerializer serializer)
+        => serializer.Serialize(writer, value);
+
+#if NET7_0_OR_GREATER
+    public static void Write(JsonWriter writer, UInt128 value, JsonSerializer serializer)
+        => writer.WriteRawValue(value.ToString(CultureInfo.InvariantCulture));
+
+    public static void Write(JsonWriter writer, Int128 value, JsonSerializer serializer)
+        => writer.WriteRawValue(value.ToString(CultureInfo.InvariantCulture));
+#endif
+
+    public static void Read<T>(JsonReader reader, JsonSerializer serializer, out T value)
+        => value = serializer.Deserialize<T>(reader)!;
+
+#if NET7_0_OR_GREATER
+    public static void Read(JsonReader reader, JsonSerializer serializer, out UInt128 value)
+        => value = UInt128.Parse(Convert.ToString(reader.Value, CultureInfo.InvariantCulture)!, CultureInfo.InvariantCulture);
+
+    public static void Read(JsonReader reader, JsonSerializer serializer, out Int128 value)
+        => value = Int128.Parse(Convert.ToString(reader.Value, CultureInfo.InvariantCulture)!, CultureInfo.InvariantCulture);
+#endif
+}
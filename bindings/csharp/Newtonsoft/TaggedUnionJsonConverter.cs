@@ -0,0 +1,91 @@
+using Newtonsoft.Json;
+using Newtonsoft.Json.Linq;
+
+namespace NeatProto.Newtonsoft;
+
+/// <summary>
+/// Base of Newtonsoft.Json converters generated for tagged unions, which (de)serializes the abstract class
+/// representing the union as one of its cases. Cases must be annotated with <see cref="DefaultJsonConverter"/>,
+/// so they are (de)serialized using the default contract instead of this converter.
+/// </summary>
+public abstract class TaggedUnionJsonConverter<TUnion> : JsonConverter<TUnion>
+    where TUnion : class
+{
+    private readonly TaggedUnionLayout _layout;
+    private readonly string _tagName;
+    private readonly string _contentName;
+    private readonly Dictionary<string, TaggedUnionCase> _casesByTag;
+    private readonly Dictionary<Type, TaggedUnionCase> _casesByType;
+
+    protected TaggedUnionJsonConverter(
+        TaggedUnionLayout layout,
+        string tagName,
+        string contentName,
+        params TaggedUnionCase[] cases)
+    {
+        _layout = layout;
+        _tagName = tagName;
+        _contentName = contentName;
+        _casesByTag = cases.ToDictionary(unionCase => unionCase.Tag);
+        _casesByType = cases.ToDictionary(unionCase => unionCase.Type);
+    }
+
+    public override TUnion? ReadJson(
+        JsonReader reader,
+        Type objectType,
+        TUnion? existingValue,
+        bool hasExistingValue,
+        JsonSerializer serializer)
+    {
+        if (reader.TokenType == JsonToken.Null)
+            return null;
+
+        if (reader.TokenType != JsonToken.StartObject)
+            throw new JsonSerializationException($"Expected object, but got {reader.TokenType}");
+
+        var root = JObject.Load(reader);
+        if (root[_tagName] is not { Type: JTokenType.String } tag)
+            throw new JsonSerializationException($"Missing '{_tagName}' property");
+
+        if (!_casesByTag.TryGetValue(tag.Value<string>()!, out var unionCase))
+            throw new JsonSerializationException($"Unknown {_tagName}: {tag.Value<string>()}");
+
+        JToken content = root;
+        if (_layout == TaggedUnionLayout.Adjacent)
+            content = root[_contentName] ?? new JObject();
+
+        using var contentReader = content.CreateReader();
+        return (TUnion)serializer.Deserialize(contentReader, unionCase.Type)!;
+    }
+
+    public override void WriteJson(JsonWriter writer, TUnion? value, JsonSerializer serializer)
+    {
+        if (value == null)
+        {
+            writer.WriteNull();
+            return;
+        }
+
+        if (!_casesByType.TryGetValue(value.GetType(), out var unionCase))
+            throw new JsonSerializationException($"Unknown case of {typeof(TUnion).Name}: {value.GetType().Name}");
+
+        writer.WriteStartObject();
+        writer.WritePropertyName(_tagName);
+        writer.WriteValue(unionCase.Tag);
+
+        switch (_layout)
+        {
+            case TaggedUnionLayout.Internal:
+                foreach (var property in JObject.FromObject(value, serializer).Properties())
+                    property.WriteTo(writer);
+                break;
+
+            case TaggedUnionLayout.Adjacent when unionCase.HasContent:
+                writer.WritePropertyName(_contentName);
+                serializer.Serialize(writer, value, unionCase.Type);
+                break;
+        }
+
+        writer.WriteEndObject();
+    }
+}
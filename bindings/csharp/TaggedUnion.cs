@@ -5,11 +5,17 @@ namespace NeatProto;
 public class TaggedUnion<TKind>
 {
     [Required]
+#if NET7_0_OR_GREATER
     public required TKind Kind { get; init; }
+#else
+    public TKind Kind { get; init; } = default!;
+#endif
 }
 
 public class TaggedUnion<TKind, TValue> : TaggedUnion<TKind>
+#if NET7_0_OR_GREATER
     where TValue : ITaggedUnionCase<TKind>
+#endif
 {
     // Disable nullability warnings, because unit enum variants do not include this
     // property in their JSON representation, and there's no way to nicely represent that in C#.
@@ -18,7 +24,9 @@ public class TaggedUnion<TKind, TValue> : TaggedUnion<TKind>
 #nullable enable
 }
 
+#if NET7_0_OR_GREATER
 public interface ITaggedUnionCase<out TKind>
 {
     public static abstract TKind Kind { get; }
-}
\ No newline at end of file
+}
+#endif
\ No newline at end of file
@@ -0,0 +1,100 @@
+using System.Text.Json;
+using System.Text.Json.Serialization;
+
+namespace NeatProto;
+
+public enum TaggedUnionLayout
+{
+    /// <summary>
+    /// Fields of the case are written next to the tag: <c>{ "kind": "...", ... }</c>.
+    /// </summary>
+    Internal,
+
+    /// <summary>
+    /// Fields of the case are written into a separate content field:
+    /// <c>{ "kind": "...", "value": { ... } }</c>.
+    /// </summary>
+    Adjacent,
+}
+
+/// <summary>
+/// Case of a tagged union, identified in JSON by its tag.
+/// </summary>
+/// <param name="Tag">Value of the tag field identifying the case.</param>
+/// <param name="Type">Class representing the case.</param>
+/// <param name="HasContent">Whether the case has any fields, cases without fields are written without the content field.</param>
+public record TaggedUnionCase(string Tag, Type Type, bool HasContent);
+
+/// <summary>
+/// Base of JSON converters generated for tagged unions, which (de)serializes the abstract class representing
+/// the union as one of its cases. Cases are (de)serialized using metadata provided by the options, so the converter
+/// works with source generated serializer contexts.
+/// </summary>
+public abstract class TaggedUnionJsonConverter<TUnion> : JsonConverter<TUnion>
+    where TUnion : class
+{
+    private static readonly JsonElement EmptyObject = JsonDocument.Parse("{}").RootElement.Clone();
+
+    private readonly TaggedUnionLayout _layout;
+    private readonly string _tagName;
+    private readonly string _contentName;
+    private readonly Dictionary<string, TaggedUnionCase> _casesByTag;
+    private readonly Dictionary<Type, TaggedUnionCase> _casesByType;
+
+    protected TaggedUnionJsonConverter(
+        TaggedUnionLayout layout,
+        string tagName,
+        string contentName,
+        params TaggedUnionCase[] cases)
+    {
+        _layout = layout;
+        _tagName = tagName;
+        _contentName = contentName;
+        _casesByTag = cases.ToDictionary(unionCase => unionCase.Tag);
+        _casesByType = cases.ToDictionary(unionCase => unionCase.Type);
+    }
+
+    public override TUnion Read(ref Utf8JsonReader reader, Type typeToConvert, JsonSerializerOptions options)
+    {
+        using var document = JsonDocument.ParseValue(ref reader);
+        var root = document.RootElement;
+        if (root.ValueKind != JsonValueKind.Object)
+            throw new JsonException($"Expected object, but got {root.ValueKind}");
+
+        if (!root.TryGetProperty(_tagName, out var tag) || tag.ValueKind != JsonValueKind.String)
+            throw new JsonException($"Missing '{_tagName}' property");
+
+        if (!_casesByTag.TryGetValue(tag.GetString()!, out var unionCase))
+            throw new JsonException($"Unknown {_tagName}: {tag.GetString()}");
+
+        var content = root;
+        if (_layout == TaggedUnionLayout.Adjacent && !root.TryGetProperty(_contentName, out content))
+            content = EmptyObject;
+
+        return (TUnion)content.Deserialize(options.GetTypeInfo(unionCase.Type))!;
+    }
+
+    public override void Write(Utf8JsonWriter writer, TUnion value, JsonSerializerOptions options)
+    {
+        if (!_casesByType.TryGetValue(value.GetType(), out var unionCase))
+            throw new JsonException($"Unknown case of {typeof(TUnion).Name}: {value.GetType().Name}");
+
+        writer.WriteStartObject();
+        writer.WriteString(_tagName, unionCase.Tag);
+
+        switch (_layout)
+        {
+            case TaggedUnionLayout.Internal:
+                foreach (var property in JsonSerializer.SerializeToElement(value, options.GetTypeInfo(unionCase.Type)).EnumerateObject())
+                    property.WriteTo(writer);
+                break;
+
+            case TaggedUnionLayout.Adjacent when unionCase.HasContent:
+                writer.WritePropertyName(_contentName);
+                JsonSerializer.Serialize(writer, value, options.GetTypeInfo(unionCase.Type));
+                break;
+        }
+
+        writer.WriteEndObject();
+    }
+}
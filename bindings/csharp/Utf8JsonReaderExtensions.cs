@@ -17,8 +17,10 @@ public static class Utf8JsonReaderExtensions
     public static void Get(this Utf8JsonReader reader, out ulong value)
         => value = reader.GetUInt64();
 
+#if NET7_0_OR_GREATER
     public static void Get(this Utf8JsonReader reader, out UInt128 value)
         => value = UInt128.Parse(Encoding.UTF8.GetString(reader.ValueSpan));
+#endif
 
     public static void Get(this Utf8JsonReader reader, out sbyte value)
         => value = reader.GetSByte();
@@ -32,8 +34,10 @@ public static class Utf8JsonReaderExtensions
     public static void Get(this Utf8JsonReader reader, out long value)
         => value = reader.GetInt64();
 
+#if NET7_0_OR_GREATER
     public static void Get(this Utf8JsonReader reader, out Int128 value)
         => value = Int128.Parse(Encoding.UTF8.GetString(reader.ValueSpan));
+#endif
 
     public static void Get(this Utf8JsonReader reader, out float value)
         => value = reader.GetSingle();
@@ -17,8 +17,10 @@ public static class Utf8JsonWriterExtensions
     public static void WriteValue(this Utf8JsonWriter writer, ulong value)
         => writer.WriteNumberValue(value);
 
+#if NET7_0_OR_GREATER
     public static void WriteValue(this Utf8JsonWriter writer, UInt128 value)
         => writer.WriteRawValue(Encoding.UTF8.GetBytes(value.ToString()), true);
+#endif
 
     public static void WriteValue(this Utf8JsonWriter writer, sbyte value)
         => writer.WriteNumberValue(value);
@@ -32,8 +34,10 @@ public static class Utf8JsonWriterExtensions
     public static void WriteValue(this Utf8JsonWriter writer, long value)
         => writer.WriteNumberValue(value);
 
+#if NET7_0_OR_GREATER
     public static void WriteValue(this Utf8JsonWriter writer, Int128 value)
         => writer.WriteRawValue(Encoding.UTF8.GetBytes(value.ToString()), true);
+#endif
 
     public static void WriteValue(this Utf8JsonWriter writer, float value)
         => writer.WriteNumberValue(value);
@@ -0,0 +1,34 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum AnalysisError {
+    DuplicateDeclaration(String),
+    DuplicateField(String, String),
+    DuplicateEnumItem(String, String),
+}
+
+impl Display for AnalysisError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisError::DuplicateDeclaration(name) => {
+                write!(f, "Type '{}' is declared more than once", name)
+            }
+            AnalysisError::DuplicateField(structure, field) => {
+                write!(
+                    f,
+                    "Field '{}' of '{}' is declared more than once",
+                    field, structure
+                )
+            }
+            AnalysisError::DuplicateEnumItem(e, item) => {
+                write!(
+                    f,
+                    "Item '{}' of enum '{}' is declared more than once",
+                    item, e
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
@@ -1,26 +1,277 @@
-use neatproto_ast::{Alias, Block, BlockNode, Enum, Structure, TypeName};
+mod error;
 
-pub fn analyze_block(block: &mut Block) {
+pub use error::*;
+
+use neatproto_ast::{
+    Alias, Block, BlockNode, BuiltinTypeName, Enum, Structure, StructureField, TypeName,
+    TypeProperties,
+};
+use std::collections::{HashMap, HashSet};
+
+pub type AnalysisResult = Result<(), AnalysisError>;
+
+pub fn analyze_block(block: &mut Block) -> AnalysisResult {
+    analyze_blocks(std::slice::from_mut(block))
+}
+
+/// Analyzes blocks of multiple source files at once, which allows them to refer to types
+/// declared in each other.
+pub fn analyze_blocks(blocks: &mut [Block]) -> AnalysisResult {
+    // Nested blocks are generated into the same scope, so names must be unique across all of them.
+    let mut declared_names = HashSet::new();
+    for block in blocks.iter_mut() {
+        visit_block(block, &mut declared_names)?;
+    }
+
+    let type_properties = compute_type_properties(blocks);
+    for block in blocks.iter_mut() {
+        assign_type_properties(block, &type_properties);
+    }
+
+    Ok(())
+}
+
+fn visit_block(block: &mut Block, declared_names: &mut HashSet<String>) -> AnalysisResult {
     for node in &mut block.nodes {
-        match node {
-            BlockNode::Block(block) => analyze_block(block),
-            BlockNode::Structure(structure) => visit_structure(structure),
-            BlockNode::Alias(alias) => visit_alias(alias),
-            BlockNode::Enum(e) => visit_enum(e),
+        let name = match node {
+            BlockNode::Block(block) => {
+                visit_block(block, declared_names)?;
+                continue;
+            }
+            BlockNode::Structure(structure) => {
+                visit_structure(structure)?;
+                &structure.name
+            }
+            BlockNode::Alias(alias) => {
+                visit_alias(alias);
+                &alias.alias_name
+            }
+            BlockNode::Enum(e) => {
+                visit_enum(e)?;
+                &e.name
+            }
+        };
+
+        if !declared_names.insert(name.clone()) {
+            return Err(AnalysisError::DuplicateDeclaration(name.clone()));
         }
     }
+    Ok(())
 }
 
-fn visit_structure(structure: &mut Structure) {
+fn visit_structure(structure: &mut Structure) -> AnalysisResult {
+    let mut field_names = HashSet::new();
     for field in &mut structure.fields {
+        if !field_names.insert(&field.name) {
+            return Err(AnalysisError::DuplicateField(
+                structure.name.clone(),
+                field.name.clone(),
+            ));
+        }
         field.type_name.resolved = resolve_type_name(&field.type_name);
     }
+    Ok(())
 }
 
 fn visit_alias(_alias: &mut Alias) {}
 
-fn visit_enum(_enum: &mut Enum) {}
+fn visit_enum(e: &mut Enum) -> AnalysisResult {
+    let mut item_names = HashSet::new();
+    for item in &mut e.items {
+        if !item_names.insert(&item.name) {
+            return Err(AnalysisError::DuplicateEnumItem(
+                e.name.clone(),
+                item.name.clone(),
+            ));
+        }
+        if let Some(structure) = &mut item.structure {
+            visit_structure(structure)?;
+        }
+    }
+    Ok(())
+}
 
 fn resolve_type_name(_type_name: &TypeName) -> Option<String> {
     None
 }
+
+fn assign_type_properties(block: &mut Block, type_properties: &HashMap<String, TypeProperties>) {
+    for node in &mut block.nodes {
+        match node {
+            BlockNode::Block(block) => assign_type_properties(block, type_properties),
+            BlockNode::Structure(structure) => {
+                structure.properties = type_properties[&structure.name];
+            }
+            BlockNode::Alias(alias) => alias.properties = type_properties[&alias.alias_name],
+            BlockNode::Enum(e) => e.properties = type_properties[&e.name],
+        }
+    }
+}
+
+/// Computes properties of all declared types. Since types may refer to each other (even
+/// recursively), properties are propagated until none of them changes.
+fn compute_type_properties(blocks: &[Block]) -> HashMap<String, TypeProperties> {
+    let mut declarations = vec![];
+    for block in blocks {
+        collect_declarations(block, &mut declarations);
+    }
+
+    let mut type_properties = declarations
+        .iter()
+        .map(|(name, _)| (name.to_string(), TypeProperties::default()))
+        .collect::<HashMap<_, _>>();
+
+    loop {
+        let mut changed = false;
+        for (name, node) in &declarations {
+            let properties = node_properties(node, &type_properties);
+            if type_properties[*name] != properties {
+                type_properties.insert(name.to_string(), properties);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return type_properties;
+        }
+    }
+}
+
+fn collect_declarations<'a>(block: &'a Block, declarations: &mut Vec<(&'a str, &'a BlockNode)>) {
+    for node in &block.nodes {
+        let name = match node {
+            BlockNode::Block(block) => {
+                collect_declarations(block, declarations);
+                continue;
+            }
+            BlockNode::Structure(structure) => &structure.name,
+            BlockNode::Alias(alias) => &alias.alias_name,
+            BlockNode::Enum(e) => &e.name,
+        };
+        declarations.push((name, node));
+    }
+}
+
+fn node_properties(
+    node: &BlockNode,
+    type_properties: &HashMap<String, TypeProperties>,
+) -> TypeProperties {
+    match node {
+        BlockNode::Structure(structure) => fields_properties(&structure.fields, type_properties),
+        BlockNode::Enum(e) => {
+            let properties = TypeProperties {
+                contains_tagged_union: e.items.iter().any(|item| item.structure.is_some()),
+                ..Default::default()
+            };
+            e.items
+                .iter()
+                .filter_map(|item| item.structure.as_ref())
+                .fold(properties, |properties, structure| {
+                    combine(
+                        properties,
+                        fields_properties(&structure.fields, type_properties),
+                    )
+                })
+        }
+        BlockNode::Alias(alias) => type_name_properties(&alias.aliased_type_name, type_properties),
+        BlockNode::Block(_) => TypeProperties::default(),
+    }
+}
+
+fn fields_properties(
+    fields: &[StructureField],
+    type_properties: &HashMap<String, TypeProperties>,
+) -> TypeProperties {
+    fields
+        .iter()
+        .fold(TypeProperties::default(), |properties, field| {
+            let type_name = field.type_name.token.value();
+            combine(
+                properties,
+                type_name_properties(&type_name, type_properties),
+            )
+        })
+}
+
+fn type_name_properties(
+    type_name: &str,
+    type_properties: &HashMap<String, TypeProperties>,
+) -> TypeProperties {
+    match BuiltinTypeName::parse(type_name) {
+        Some(BuiltinTypeName::Float32 | BuiltinTypeName::Float64) => TypeProperties {
+            contains_float: true,
+            ..Default::default()
+        },
+        // Types which are not declared can not be analyzed.
+        _ => type_properties.get(type_name).copied().unwrap_or_default(),
+    }
+}
+
+fn combine(a: TypeProperties, b: TypeProperties) -> TypeProperties {
+    TypeProperties {
+        contains_float: a.contains_float || b.contains_float,
+        contains_tagged_union: a.contains_tagged_union || b.contains_tagged_union,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyze_block;
+    use neatproto_ast::{BlockNode, TypeProperties};
+    use neatproto_parser::{SourceFile, parse_block};
+
+    fn analyze_properties(source: &str) -> Vec<(String, TypeProperties)> {
+        let source_file = SourceFile::new_from_source("test", source);
+        let mut block = parse_block(&mut source_file.tokens()).expect("failed to parse");
+        analyze_block(&mut block).expect("failed to analyze");
+
+        block
+            .nodes
+            .iter()
+            .map(|node| match node {
+                BlockNode::Structure(structure) => (structure.name.clone(), structure.properties),
+                BlockNode::Enum(e) => (e.name.clone(), e.properties),
+                BlockNode::Alias(alias) => (alias.alias_name.clone(), alias.properties),
+                BlockNode::Block(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_type_properties() {
+        let properties = analyze_properties(
+            r#"
+            struct Tree { children: Tree[]; value: Value; }
+            alias Value = Point;
+            struct Point { x: float; }
+            enum Shape { Circle { radius: uint32; }, Empty }
+            struct Plain { id: uint32; }
+            "#,
+        );
+
+        let contains_float = properties
+            .iter()
+            .map(|(name, properties)| (name.as_str(), properties.contains_float))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            contains_float,
+            vec![
+                ("Tree", true),
+                ("Value", true),
+                ("Point", true),
+                ("Shape", false),
+                ("Plain", false)
+            ]
+        );
+        assert!(properties[3].1.contains_tagged_union);
+        assert!(!properties[4].1.contains_tagged_union);
+    }
+
+    #[test]
+    fn test_duplicate_declaration() {
+        let source_file = SourceFile::new_from_source("test", "struct Foo {} alias Foo = Bar;");
+        let mut block = parse_block(&mut source_file.tokens()).expect("failed to parse");
+        let error = analyze_block(&mut block).expect_err("duplicate should be reported");
+        assert_eq!(error.to_string(), "Type 'Foo' is declared more than once");
+    }
+}
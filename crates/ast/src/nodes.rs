@@ -1,4 +1,4 @@
-use crate::{LocalizedToken, TypeName};
+use crate::{LocalizedToken, SourceLocation, TypeName};
 
 #[derive(Debug)]
 pub struct Block {
@@ -17,6 +17,8 @@ pub enum BlockNode {
 pub struct Structure {
     pub name: String,
     pub fields: Vec<StructureField>,
+    pub annotations: Vec<Annotation>,
+    pub properties: TypeProperties,
 }
 
 #[derive(Debug)]
@@ -29,12 +31,16 @@ pub struct StructureField {
 pub struct Alias {
     pub alias_name: String,
     pub aliased_type_name: String,
+    pub annotations: Vec<Annotation>,
+    pub properties: TypeProperties,
 }
 
 #[derive(Debug)]
 pub struct Enum {
     pub name: String,
     pub items: Vec<EnumItem>,
+    pub annotations: Vec<Annotation>,
+    pub properties: TypeProperties,
 }
 
 #[derive(Debug)]
@@ -43,3 +49,34 @@ pub struct EnumItem {
     pub structure: Option<Structure>,
     pub value_token: Option<LocalizedToken>,
 }
+
+/// Annotation of a declaration, e.g. `@rust_attr("serde(deny_unknown_fields)")`, which allows
+/// passing target specific information to code generators.
+#[derive(Debug)]
+pub struct Annotation {
+    pub name: String,
+    pub arguments: Vec<String>,
+    pub location: SourceLocation,
+}
+
+impl Annotation {
+    /// Returns all annotations with given name.
+    pub fn find_all<'a>(
+        annotations: &'a [Annotation],
+        name: &str,
+    ) -> impl Iterator<Item = &'a Annotation> {
+        annotations
+            .iter()
+            .filter(move |annotation| annotation.name == name)
+    }
+}
+
+/// Properties of a declared type, including the types it refers to, filled in by analysis.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TypeProperties {
+    /// Type contains floating point numbers, so it can not be compared for total equality
+    /// or hashed.
+    pub contains_float: bool,
+    /// Type contains a tagged union, which has no natural default value.
+    pub contains_tagged_union: bool,
+}
@@ -42,10 +42,15 @@ impl Token {
 
     pub fn len(&self) -> usize {
         match self {
-            Token::Identifier(value) | Token::Digit(value) | Token::String(value) => value.len(),
+            Token::Identifier(value) | Token::Digit(value) => value.len(),
+            Token::String(value) => value.len() + 2,
             _ => 1,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 #[derive(Debug)]
@@ -62,4 +67,8 @@ impl LocalizedToken {
     pub fn len(&self) -> usize {
         self.token.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.token.is_empty()
+    }
 }
@@ -0,0 +1,248 @@
+use clap::{ArgAction, Args};
+use neatproto_codegen::{
+    CSharpCodeGenOptions, CSharpJsonLibrary, CSharpStructureKind, CodeGenOptions,
+    JsonSchemaCodeGenOptions, KotlinCodeGenOptions, NameCase, RustCodeGenOptions,
+    SwiftCodeGenOptions, TaggedUnionRepr, TargetLanguage,
+};
+
+// Command line representation of `CodeGenOptions`. Name cases accept values like `camel`,
+// `snake`, `pascal`, `kebab`, `upper-snake` or `unchanged`.
+#[derive(Args, Debug, Clone)]
+pub struct CodeGenArgs {
+    /// Case of structure field names.
+    #[arg(long, default_value = "unchanged")]
+    field_name_case: NameCase,
+
+    /// Case of type names.
+    #[arg(long, default_value = "unchanged")]
+    type_name_case: NameCase,
+
+    /// Case of enum item names.
+    #[arg(long, default_value = "unchanged")]
+    enum_item_name_case: NameCase,
+
+    /// JSON representation of tagged unions. Not every target supports every representation.
+    #[arg(long, value_enum, default_value_t = TaggedUnionRepr::default())]
+    tagged_union_repr: TaggedUnionRepr,
+
+    /// Derive `Debug` for generated types.
+    #[arg(long, help_heading = "Rust")]
+    rust_debug: bool,
+
+    /// Derive serde's `Serialize` and `Deserialize` for generated types.
+    #[arg(long, help_heading = "Rust")]
+    rust_serde: bool,
+
+    /// Case passed to `#[serde(rename_all = ...)]`.
+    #[arg(long, default_value = "unchanged", help_heading = "Rust")]
+    rust_serde_field_name_case: NameCase,
+
+    /// Serialize plain enums as numbers using `serde_repr` with given representation (e.g. `u8`).
+    #[arg(long, value_name = "REPR", help_heading = "Rust")]
+    rust_serde_enum_repr: Option<String>,
+
+    /// Implement `Display` for plain enums.
+    #[arg(long, help_heading = "Rust")]
+    rust_enum_display: bool,
+
+    /// When generating one file per input, generate a module per input along with `mod.rs`
+    /// files declaring them and importing types used across inputs.
+    #[arg(long, help_heading = "Rust")]
+    rust_module_tree: bool,
+
+    /// Refer to serde traits using fully qualified paths instead of importing them.
+    #[arg(long, help_heading = "Rust")]
+    rust_serde_qualified_paths: bool,
+
+    /// Start generated files with `#![allow(...)]` for lints triggered by generated code.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        action = ArgAction::Set,
+        default_value_t = RustCodeGenOptions::default().with_allow_lints,
+        help_heading = "Rust"
+    )]
+    rust_allow_lints: bool,
+
+    /// Additional trait to derive, e.g. `PartialEq` or `Hash`, skipped for types it is not
+    /// valid for (like `Eq` for types containing floats). Can be given multiple times.
+    #[arg(
+        long,
+        value_name = "TRAIT",
+        value_delimiter = ',',
+        help_heading = "Rust"
+    )]
+    rust_derive: Vec<String>,
+
+    /// Namespace of generated types.
+    #[arg(long, default_value_t = CSharpCodeGenOptions::default().namespace, help_heading = "C#")]
+    csharp_namespace: String,
+
+    /// Generate JSON converters for tagged union discriminators.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        action = ArgAction::Set,
+        default_value_t = CSharpCodeGenOptions::default().with_json_convert_for_union_tags,
+        help_heading = "C#"
+    )]
+    csharp_json_convert_for_union_tags: bool,
+
+    /// Generate every top-level type into its own file, next to the output path.
+    #[arg(long, help_heading = "C#")]
+    csharp_file_per_type: bool,
+
+    /// Serialize tagged unions using `[JsonPolymorphic]` attributes (.NET 7+) instead of
+    /// converters from the support package. Requires internally tagged unions.
+    #[arg(long, help_heading = "C#")]
+    csharp_json_polymorphic: bool,
+
+    /// Generate a `JsonSerializerContext` with given name for all types, to be used with
+    /// System.Text.Json source generation.
+    #[arg(long, value_name = "NAME", help_heading = "C#")]
+    csharp_json_serializer_context: Option<String>,
+
+    /// Kind of type that structures are generated as.
+    #[arg(long, value_enum, default_value_t = CSharpStructureKind::default(), help_heading = "C#")]
+    csharp_structure_kind: CSharpStructureKind,
+
+    /// Generate properties with `init` instead of `set` accessors.
+    #[arg(long, help_heading = "C#")]
+    csharp_init_only_setters: bool,
+
+    /// Mark properties with `[Required]` from `System.ComponentModel.DataAnnotations`.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        action = ArgAction::Set,
+        default_value_t = CSharpCodeGenOptions::default().with_required_attribute,
+        help_heading = "C#"
+    )]
+    csharp_required_attribute: bool,
+
+    /// Version of C# that generated code must compile with (9 or newer).
+    #[arg(
+        long,
+        value_name = "VERSION",
+        default_value_t = CSharpCodeGenOptions::default().language_version,
+        help_heading = "C#"
+    )]
+    csharp_language_version: u32,
+
+    /// Library that generated JSON converters and attributes are written for.
+    #[arg(long, value_enum, default_value_t = CSharpJsonLibrary::default(), help_heading = "C#")]
+    csharp_json_library: CSharpJsonLibrary,
+
+    /// Generate `Equals`, `GetHashCode`, `==` and `ToString` comparing and printing types by
+    /// their fields.
+    #[arg(long, help_heading = "C#")]
+    csharp_equality: bool,
+
+    /// Package of generated types.
+    #[arg(long, default_value_t = KotlinCodeGenOptions::default().package, help_heading = "Kotlin")]
+    kotlin_package: String,
+
+    /// Annotate generated types for kotlinx.serialization.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        action = ArgAction::Set,
+        default_value_t = KotlinCodeGenOptions::default().with_serialization,
+        help_heading = "Kotlin"
+    )]
+    kotlin_serialization: bool,
+
+    /// Access modifier of generated declarations, empty for the default (internal) access.
+    #[arg(long, default_value_t = SwiftCodeGenOptions::default().access_modifier, help_heading = "Swift")]
+    swift_access_modifier: String,
+
+    /// Value of the `$id` keyword of generated schema.
+    #[arg(long, value_name = "ID", help_heading = "JSON Schema")]
+    json_schema_id: Option<String>,
+
+    /// Option of a target registered by the program embedding the CLI, given as `KEY=VALUE`.
+    /// Can be given multiple times.
+    #[arg(
+        long = "option",
+        value_name = "KEY=VALUE",
+        value_parser = parse_custom_option,
+        help_heading = "Custom targets"
+    )]
+    custom_options: Vec<(String, String)>,
+}
+
+fn parse_custom_option(string: &str) -> Result<(String, String), String> {
+    string
+        .split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", string))
+}
+
+impl CodeGenArgs {
+    /// Converts arguments into options of given target, either built-in or registered by the
+    /// program embedding the CLI.
+    pub fn into_target_options(self, target: &str) -> CodeGenOptions {
+        match TargetLanguage::from_name(target) {
+            Some(target_language) => self.into_options(target_language),
+            None => self.into_custom_options(),
+        }
+    }
+
+    /// Converts arguments into options of a target registered by the program embedding the
+    /// CLI, which only receives target-independent options and those given with `--option`.
+    pub fn into_custom_options(self) -> CodeGenOptions {
+        CodeGenOptions {
+            field_name_case: self.field_name_case,
+            type_name_case: self.type_name_case,
+            enum_item_name_case: self.enum_item_name_case,
+            tagged_union_repr: self.tagged_union_repr,
+            custom: self.custom_options.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn into_options(self, target_language: TargetLanguage) -> CodeGenOptions {
+        CodeGenOptions {
+            target_language,
+            field_name_case: self.field_name_case,
+            type_name_case: self.type_name_case,
+            enum_item_name_case: self.enum_item_name_case,
+            tagged_union_repr: self.tagged_union_repr,
+            rust: RustCodeGenOptions {
+                with_debug: self.rust_debug,
+                with_serde: self.rust_serde,
+                serde_struct_field_name_case: self.rust_serde_field_name_case,
+                serde_enum_repr: self.rust_serde_enum_repr,
+                with_enum_display: self.rust_enum_display,
+                module_tree: self.rust_module_tree,
+                serde_qualified_paths: self.rust_serde_qualified_paths,
+                with_allow_lints: self.rust_allow_lints,
+                extra_derives: self.rust_derive,
+            },
+            csharp: CSharpCodeGenOptions {
+                namespace: self.csharp_namespace,
+                with_json_convert_for_union_tags: self.csharp_json_convert_for_union_tags,
+                file_per_type: self.csharp_file_per_type,
+                with_json_polymorphic: self.csharp_json_polymorphic,
+                json_serializer_context: self.csharp_json_serializer_context,
+                structure_kind: self.csharp_structure_kind,
+                init_only_setters: self.csharp_init_only_setters,
+                with_required_attribute: self.csharp_required_attribute,
+                language_version: self.csharp_language_version,
+                json_library: self.csharp_json_library,
+                with_equality: self.csharp_equality,
+            },
+            kotlin: KotlinCodeGenOptions {
+                package: self.kotlin_package,
+                with_serialization: self.kotlin_serialization,
+            },
+            swift: SwiftCodeGenOptions {
+                access_modifier: self.swift_access_modifier,
+            },
+            json_schema: JsonSchemaCodeGenOptions {
+                id: self.json_schema_id,
+            },
+            custom: self.custom_options.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,535 @@
+mod codegen_args;
+mod verify;
+mod watch;
+
+use crate::codegen_args::CodeGenArgs;
+use crate::verify::verify_output_file;
+use crate::watch::watch;
+use clap::{Args, Parser, Subcommand};
+use neatproto_codegen::{CodeGenOptions, CodeGenRegistry};
+use neatproto_compiler::inputs::{InputFile, collect_input_files};
+use neatproto_compiler::project::{PROJECT_CONFIG_FILE_NAME, Project};
+use neatproto_compiler::{
+    CompilationUnit, CompileError, SourceFile, read_source_file, split_output_file_path,
+    write_output_file,
+};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Compile a schema file into code of given target.
+    Compile(Box<CompileCommandArgs>),
+    /// Parse and analyze schema files without generating any code.
+    Check(Box<CheckCommandArgs>),
+    /// Generate code for every target described in a project config file.
+    Build(BuildCommandArgs),
+}
+
+#[derive(Args, Debug)]
+struct BuildCommandArgs {
+    /// Path to the project config file.
+    #[arg(short, long, default_value = PROJECT_CONFIG_FILE_NAME)]
+    config: String,
+}
+
+#[derive(Args, Debug)]
+struct CheckCommandArgs {
+    /// Input files or directories, which are searched recursively for `*.nproto` files.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Name of a target to validate schemas for, reporting everything that `compile` would
+    /// reject with the same options.
+    #[arg(short, long)]
+    target: Option<String>,
+
+    #[command(flatten)]
+    codegen: CodeGenArgs,
+}
+
+#[derive(Args, Debug)]
+struct CompileCommandArgs {
+    /// Input files or directories, which are searched recursively for `*.nproto` files.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output file or, when compiling multiple inputs without `--merge`, output directory
+    /// mirroring the structure of input directories. Code is printed to stdout if omitted.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Merge all inputs into a single output file.
+    #[arg(long)]
+    merge: bool,
+
+    /// Keep running and recompile inputs whenever they change.
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Instead of writing output files, compare them with freshly generated code and fail
+    /// with a diff if they are out of date.
+    #[arg(long, requires = "output", conflicts_with = "watch")]
+    verify: bool,
+
+    /// Name of the target to generate code for, either built-in or registered by the
+    /// program embedding the CLI.
+    #[arg(short, long, required_unless_present = "plugin")]
+    target: Option<String>,
+
+    /// Generate code using external `neatproto-gen-<PLUGIN>` executable found on PATH.
+    /// Output is treated as a directory, since plugins may generate multiple files.
+    #[arg(long, conflicts_with = "target")]
+    plugin: Option<String>,
+
+    /// Parameter passed as-is to the plugin.
+    #[arg(long, requires = "plugin")]
+    plugin_param: Option<String>,
+
+    #[command(flatten)]
+    codegen: CodeGenArgs,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Compile(CompileError),
+    /// Number of errors found by the check.
+    Invalid(usize),
+    /// Number of output files which are not up to date.
+    Stale(usize),
+    Watch(notify::Error),
+    /// Multiple inputs are compiled without `--merge` and without an output directory.
+    MissingOutputDir,
+}
+
+impl From<CompileError> for CliError {
+    fn from(error: CompileError) -> Self {
+        CliError::Compile(error)
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Compile(error) => write!(f, "{}", error),
+            CliError::Invalid(count) => write!(f, "Found {} error(s)", count),
+            CliError::Stale(count) => write!(
+                f,
+                "{} generated file(s) are out of date, run the same command without --verify \
+                 to regenerate them",
+                count
+            ),
+            CliError::Watch(error) => write!(f, "Failed to watch inputs: {}", error),
+            CliError::MissingOutputDir => write!(
+                f,
+                "--output directory is required when compiling multiple inputs without --merge"
+            ),
+        }
+    }
+}
+
+fn compile(registry: &CodeGenRegistry, args: CompileCommandArgs) -> Result<(), CliError> {
+    let target = args.target.clone().unwrap_or_default();
+    let options = args.codegen.clone().into_target_options(&target);
+
+    if args.verify {
+        let outputs = generate_outputs(registry, &args, &options, None)?;
+        let mut stale_files = 0;
+        for (path, code) in outputs {
+            let path = path.expect("--verify requires --output");
+            if !verify_output_file(&path, &code).map_err(|error| CompileError::Io(path, error))? {
+                stale_files += 1;
+            }
+        }
+
+        return match stale_files {
+            0 => Ok(()),
+            count => Err(CliError::Stale(count)),
+        };
+    }
+
+    if !args.watch {
+        emit_outputs(generate_outputs(registry, &args, &options, None)?, false)?;
+        return Ok(());
+    }
+
+    let compile = |changed_files: Option<&HashSet<PathBuf>>| {
+        match generate_outputs(registry, &args, &options, changed_files) {
+            Ok(outputs) => match emit_outputs(outputs, true) {
+                Ok(written_files) => {
+                    for path in written_files {
+                        println!("Generated {}", path.display());
+                    }
+                }
+                Err(err) => println!("{}", err),
+            },
+            Err(err) => println!("{}", err),
+        }
+        println!("Watching for changes...");
+    };
+
+    compile(None);
+    watch(&args.inputs, |changed_files| compile(Some(changed_files))).map_err(CliError::Watch)
+}
+
+/// Writes generated code into output files, or prints it to stdout when there is no output
+/// path, and returns paths of written files. Files whose contents would not change are
+/// skipped if `skip_unchanged` is set.
+fn emit_outputs(
+    outputs: Vec<(Option<PathBuf>, String)>,
+    skip_unchanged: bool,
+) -> Result<Vec<PathBuf>, CompileError> {
+    let mut written_files = vec![];
+    for (path, code) in outputs {
+        match path {
+            Some(path) => {
+                if skip_unchanged && std::fs::read_to_string(&path).is_ok_and(|old| old == code) {
+                    continue;
+                }
+
+                write_output_file(path.clone(), code)?;
+                written_files.push(path);
+            }
+            None => println!("{}", code),
+        }
+    }
+    Ok(written_files)
+}
+
+/// Generates code for inputs given on the command line and returns it along with paths of
+/// output files it belongs to. Path is `None` when no output was given.
+///
+/// When absolute paths of changed files are given and every input is generated into its own
+/// file, only outputs of inputs affected by the change are returned.
+fn generate_outputs(
+    registry: &CodeGenRegistry,
+    args: &CompileCommandArgs,
+    options: &CodeGenOptions,
+    changed_files: Option<&HashSet<PathBuf>>,
+) -> Result<Vec<(Option<PathBuf>, String)>, CliError> {
+    let input_files = collect_input_files(&args.inputs)?;
+    let source_files = read_source_files(&input_files)?;
+
+    if let Some(plugin) = &args.plugin {
+        let unit = CompilationUnit::new_merged(&source_files);
+        let files = unit.compile_with_plugin(plugin, args.plugin_param.clone())?;
+        return Ok(files
+            .into_iter()
+            .map(|file| {
+                let path = args.output.as_ref().map(|output| output.join(file.name));
+                (path, file.content)
+            })
+            .collect());
+    }
+
+    let target = args.target.clone().unwrap_or_default();
+    let generator = registry
+        .get(&target)
+        .ok_or_else(|| CompileError::UnknownTarget(target.clone()))?;
+
+    let is_single_file = args.inputs.len() == 1 && !args.inputs[0].is_dir();
+    if args.merge || is_single_file {
+        let unit = CompilationUnit::new_merged(&source_files);
+
+        let Some(output) = &args.output else {
+            let code = unit.compile_with_generator(generator, options)?;
+            return Ok(vec![(None, code)]);
+        };
+
+        let (output_dir, file_name) = split_output_file_path(output);
+        let files = unit.compile_files_with_generator(generator, options, file_name)?;
+        return Ok(files
+            .into_iter()
+            .map(|(file_path, code)| (Some(output_dir.join(file_path)), code))
+            .collect());
+    }
+
+    let Some(output_dir) = &args.output else {
+        return Err(CliError::MissingOutputDir);
+    };
+
+    let file_paths = input_files
+        .iter()
+        .map(|input_file| {
+            input_file
+                .relative_path
+                .with_extension(generator.file_extension())
+        })
+        .collect::<Vec<_>>();
+
+    // Outputs of unaffected inputs are still generated, since all inputs have to be analyzed
+    // together, but they are not returned.
+    let mut unaffected_file_paths = HashSet::new();
+    if let Some(changed_files) = changed_files {
+        let affected =
+            CompilationUnit::new_merged(&source_files).affected_source_files(|source_file| {
+                std::path::absolute(&source_file.path)
+                    .is_ok_and(|path| changed_files.contains(&path))
+            })?;
+        for (source_file, file_path) in source_files.iter().zip(&file_paths) {
+            if !affected.contains(&source_file.path) {
+                unaffected_file_paths.insert(file_path.clone());
+            }
+        }
+    }
+
+    let module_paths = file_paths.iter().cloned().collect::<HashSet<_>>();
+    let files = CompilationUnit::new_merged(&source_files)
+        .compile_modules_with_generator(generator, options, file_paths)?;
+
+    // Outputs split per type, or otherwise not named after inputs, can't be attributed to
+    // inputs, so all of them are returned.
+    if options.csharp.file_per_type
+        || !files
+            .keys()
+            .all(|file_path| module_paths.contains(file_path))
+    {
+        unaffected_file_paths.clear();
+    }
+
+    Ok(files
+        .into_iter()
+        .filter(|(file_path, _)| !unaffected_file_paths.contains(file_path))
+        .map(|(file_path, code)| (Some(output_dir.join(file_path)), code))
+        .collect())
+}
+
+fn read_source_files(input_files: &[InputFile]) -> Result<Vec<SourceFile>, CompileError> {
+    input_files
+        .iter()
+        .map(|input_file| read_source_file(input_file.path.clone()))
+        .collect()
+}
+
+fn check(registry: &CodeGenRegistry, args: CheckCommandArgs) -> Result<(), CliError> {
+    let input_files = collect_input_files(&args.inputs)?;
+    let source_files = read_source_files(&input_files)?;
+
+    let generator = match &args.target {
+        Some(target) => Some(
+            registry
+                .get(target)
+                .ok_or_else(|| CompileError::UnknownTarget(target.clone()))?,
+        ),
+        None => None,
+    };
+    let options = args
+        .codegen
+        .into_target_options(args.target.as_deref().unwrap_or_default());
+
+    let mut errors = 0;
+    for source_file in &source_files {
+        if let Err(err) = CompilationUnit::new(source_file).check(generator, &options) {
+            println!("{}", err);
+            errors += 1;
+        }
+    }
+
+    // Errors involving multiple files, e.g. duplicate declarations, are only found once
+    // every file is valid on its own.
+    if errors == 0
+        && let Err(err) = CompilationUnit::new_merged(&source_files).check(generator, &options)
+    {
+        println!("{}", err);
+        errors += 1;
+    }
+
+    match errors {
+        0 => {
+            println!("Checked {} file(s), no errors found", source_files.len());
+            Ok(())
+        }
+        count => Err(CliError::Invalid(count)),
+    }
+}
+
+fn build(registry: &CodeGenRegistry, args: BuildCommandArgs) -> Result<(), CompileError> {
+    let project = Project::load(args.config.into())?;
+    for path in project.build(registry)? {
+        println!("Generated {}", path.display());
+    }
+    Ok(())
+}
+
+/// Runs the command line interface using code generators from given registry.
+///
+/// Programs that want to expose custom backends through the CLI can register them in a
+/// registry and call this function from their own `main`.
+pub fn run(registry: CodeGenRegistry) {
+    let args = Cli::parse();
+    let result = match args.command {
+        Commands::Compile(args) => compile(&registry, *args),
+        Commands::Check(args) => check(&registry, *args),
+        Commands::Build(args) => build(&registry, args).map_err(CliError::from),
+    };
+
+    if let Err(err) = result {
+        println!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cli, CliError, Commands, check, compile, generate_outputs};
+    use clap::Parser;
+    use neatproto_codegen::CodeGenRegistry;
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    fn run_check(files: &[(&str, &str)], args: &[&str]) -> Result<(), CliError> {
+        let dir = tempfile::tempdir().unwrap();
+        for (file_name, source) in files {
+            std::fs::write(dir.path().join(file_name), source).unwrap();
+        }
+
+        let dir_path = dir.path().to_string_lossy().to_string();
+        let cli = Cli::parse_from(["neatproto-cli", "check", &dir_path].iter().chain(args));
+        let Commands::Check(args) = cli.command else {
+            unreachable!()
+        };
+        check(&CodeGenRegistry::default(), *args)
+    }
+
+    #[test]
+    fn test_compile_without_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.nproto"), "struct A { x: bool; }").unwrap();
+        std::fs::write(dir.path().join("b.nproto"), "struct B { x: bool; }").unwrap();
+
+        let dir_path = dir.path().to_string_lossy().to_string();
+        let cli = Cli::parse_from(["neatproto-cli", "compile", &dir_path, "-t", "rust"]);
+        let Commands::Compile(args) = cli.command else {
+            unreachable!()
+        };
+        assert!(matches!(
+            compile(&CodeGenRegistry::default(), *args),
+            Err(CliError::MissingOutputDir)
+        ));
+    }
+
+    /// Generates outputs after `changed_file` has changed and returns their names.
+    fn changed_outputs(files: &[(&str, &str)], changed_file: &str, args: &[&str]) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        for (file_name, source) in files {
+            std::fs::write(dir.path().join(file_name), source).unwrap();
+        }
+
+        let dir_path = dir.path().to_string_lossy().to_string();
+        let output_path = dir.path().join("out").to_string_lossy().to_string();
+        let cli = Cli::parse_from(
+            ["neatproto-cli", "compile", &dir_path, "-o", &output_path]
+                .iter()
+                .chain(args),
+        );
+        let Commands::Compile(args) = cli.command else {
+            unreachable!()
+        };
+
+        let target = args.target.clone().unwrap_or_default();
+        let options = args.codegen.clone().into_target_options(&target);
+        let changed_files =
+            HashSet::from([std::path::absolute(dir.path().join(changed_file)).unwrap()]);
+        let outputs = generate_outputs(
+            &CodeGenRegistry::default(),
+            &args,
+            &options,
+            Some(&changed_files),
+        )
+        .unwrap();
+
+        let mut names = outputs
+            .into_iter()
+            .map(|(path, _)| {
+                let path: PathBuf = path.unwrap();
+                path.file_name().unwrap().to_string_lossy().to_string()
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_changed_outputs() {
+        let files = [
+            ("a.nproto", "struct A { x: bool; }"),
+            ("b.nproto", "struct B { x: bool; }"),
+        ];
+        assert_eq!(
+            changed_outputs(&files, "a.nproto", &["-t", "rust"]),
+            vec!["a.rs"]
+        );
+    }
+
+    #[test]
+    fn test_changed_outputs_file_per_type() {
+        // Type files don't correspond to inputs, even when their names happen to match.
+        let files = [
+            ("A.nproto", "struct B { x: bool; }"),
+            ("B.nproto", "struct A { x: bool; }"),
+        ];
+        assert_eq!(
+            changed_outputs(
+                &files,
+                "B.nproto",
+                &["-t", "csharp", "--csharp-file-per-type"]
+            ),
+            vec!["A.cs", "B.cs"]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(run_check(&[("a.nproto", "struct A { x: bool; }")], &[]).is_ok());
+        assert!(matches!(
+            run_check(&[("a.nproto", "struct A { x: bool }")], &[]),
+            Err(CliError::Invalid(1))
+        ));
+    }
+
+    #[test]
+    fn test_check_duplicate_declarations() {
+        let files = [
+            ("a.nproto", "struct A { x: bool; }"),
+            ("b.nproto", "struct A { y: bool; }"),
+        ];
+        assert!(matches!(run_check(&files, &[]), Err(CliError::Invalid(1))));
+    }
+
+    #[test]
+    fn test_check_annotations() {
+        let files = [(
+            "a.nproto",
+            "@rust_atr(\"derive(Hash)\") struct A { x: bool; }",
+        )];
+        assert!(matches!(run_check(&files, &[]), Err(CliError::Invalid(1))));
+        assert!(matches!(
+            run_check(&files, &["-t", "rust"]),
+            Err(CliError::Invalid(1))
+        ));
+    }
+
+    #[test]
+    fn test_check_target() {
+        let files = [("a.nproto", "enum Shape { Circle { r: float; }, Empty }")];
+        let args = ["--tagged-union-repr", "untagged"];
+        assert!(run_check(&files, &args).is_ok());
+        assert!(run_check(&files, &[&args[..], &["-t", "rust"]].concat()).is_ok());
+        assert!(matches!(
+            run_check(&files, &[&args[..], &["-t", "csharp"]].concat()),
+            Err(CliError::Invalid(1))
+        ));
+        assert!(matches!(
+            run_check(&files, &["-t", "unknown"]),
+            Err(CliError::Compile(_))
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+use similar::TextDiff;
+use std::path::Path;
+
+/// Compares contents of an output file with freshly generated code, printing a unified diff
+/// if they differ. Returns whether the file is up to date.
+pub fn verify_output_file(path: &Path, code: &str) -> std::io::Result<bool> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            println!("{} does not exist", path.display());
+            return Ok(false);
+        }
+        Err(error) => return Err(error),
+    };
+
+    if existing == code {
+        return Ok(true);
+    }
+
+    let path = path.display().to_string();
+    let diff = TextDiff::from_lines(existing.as_str(), code);
+    print!(
+        "{}",
+        diff.unified_diff()
+            .header(&path, &format!("{} (generated)", path))
+    );
+    Ok(false)
+}
@@ -0,0 +1,92 @@
+use neatproto_compiler::inputs::SOURCE_FILE_EXTENSION;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Editors often save files in several steps (e.g. write to a temporary file and rename it),
+/// so events are collected for a short while before reporting them.
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(100);
+
+/// Watches given input files and directories, calling `on_change` with absolute paths of
+/// changed source files. Never returns, unless the watcher could not be created or stops
+/// delivering events.
+pub fn watch<F: FnMut(&HashSet<PathBuf>)>(
+    inputs: &[PathBuf],
+    mut on_change: F,
+) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+
+    let inputs = inputs
+        .iter()
+        .map(|input| std::path::absolute(input).unwrap_or_else(|_| input.clone()))
+        .collect::<Vec<_>>();
+
+    for input in &inputs {
+        // Single files are watched through their parent directory, because editors which
+        // replace files on save would otherwise silently end the watch.
+        let (path, mode) = if input.is_dir() {
+            (input.as_path(), RecursiveMode::Recursive)
+        } else {
+            (
+                input.parent().unwrap_or(Path::new(".")),
+                RecursiveMode::NonRecursive,
+            )
+        };
+
+        if let Err(error) = watcher.watch(path, mode) {
+            println!("Failed to watch '{}': {}", path.display(), error);
+        }
+    }
+
+    while let Ok(event) = receiver.recv() {
+        let mut changed_files = HashSet::new();
+        collect_changed_files(&inputs, event, &mut changed_files);
+
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE_DURATION) {
+            collect_changed_files(&inputs, event, &mut changed_files);
+        }
+
+        if !changed_files.is_empty() {
+            on_change(&changed_files);
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_changed_files(
+    inputs: &[PathBuf],
+    event: notify::Result<Event>,
+    changed_files: &mut HashSet<PathBuf>,
+) {
+    let Ok(event) = event else {
+        return;
+    };
+
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) {
+        return;
+    }
+
+    for path in event.paths {
+        let is_input = inputs.iter().any(|input| {
+            if input.is_dir() {
+                path.starts_with(input)
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == SOURCE_FILE_EXTENSION)
+            } else {
+                &path == input
+            }
+        });
+
+        if is_input {
+            changed_files.insert(path);
+        }
+    }
+}
@@ -1,12 +1,87 @@
+use crate::tagged_union::tag_value;
 use crate::writer::IndentedWriter;
-use crate::{CodeGenOptions, NameCasing};
+use crate::{
+    CodeGenError, CodeGenOptions, CodeGenerator, NameCasing, TaggedUnionLayout, TaggedUnionRepr,
+};
 use convert_case::{Case, Casing};
 use neatproto_ast::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CSharpCodeGenOptions {
     pub namespace: String,
     pub with_json_convert_for_union_tags: bool,
+    /// Generate every top-level type into its own file named after the type.
+    pub file_per_type: bool,
+    /// Serialize tagged unions using `[JsonPolymorphic]` and `[JsonDerivedType]` attributes
+    /// (.NET 7+) instead of converters from the support package. Requires internally tagged
+    /// unions.
+    pub with_json_polymorphic: bool,
+    /// Name of a `JsonSerializerContext` to generate for all types, which allows using
+    /// System.Text.Json source generation instead of reflection (e.g. with NativeAOT).
+    pub json_serializer_context: Option<String>,
+    /// Kind of type that structures are generated as.
+    pub structure_kind: CSharpStructureKind,
+    /// Generate properties with `init` instead of `set` accessors. Properties of readonly
+    /// structs always use `init`.
+    pub init_only_setters: bool,
+    /// Mark properties with `[Required]` from `System.ComponentModel.DataAnnotations`.
+    pub with_required_attribute: bool,
+    /// Version of C# that generated code must compile with, at least 9. Before C# 11 types
+    /// are initialized using constructors instead of `required` properties, and before C# 10
+    /// namespaces are not file scoped and records are not used.
+    pub language_version: u32,
+    /// Library that generated JSON converters and attributes are written for.
+    pub json_library: CSharpJsonLibrary,
+    /// Generate `Equals`, `GetHashCode`, `==` and `ToString` comparing and printing structures
+    /// and cases of tagged unions by their fields, with lists compared element-wise.
+    pub with_equality: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CSharpStructureKind {
+    #[default]
+    Class,
+    RecordClass,
+    RecordStruct,
+    ReadonlyStruct,
+}
+
+impl CSharpStructureKind {
+    fn keyword(&self) -> &'static str {
+        match self {
+            CSharpStructureKind::Class => "class",
+            CSharpStructureKind::RecordClass => "record class",
+            CSharpStructureKind::RecordStruct => "record struct",
+            CSharpStructureKind::ReadonlyStruct => "readonly struct",
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CSharpJsonLibrary {
+    #[default]
+    SystemTextJson,
+    NewtonsoftJson,
+}
+
+impl CSharpJsonLibrary {
+    /// Attribute assigning given converter to a type.
+    fn converter_attribute(&self, converter_class_name: &str) -> String {
+        match self {
+            CSharpJsonLibrary::SystemTextJson => format!(
+                "[global::System.Text.Json.Serialization.JsonConverter(\
+                 typeof({converter_class_name}))]"
+            ),
+            CSharpJsonLibrary::NewtonsoftJson => {
+                format!("[global::Newtonsoft.Json.JsonConverter(typeof({converter_class_name}))]")
+            }
+        }
+    }
 }
 
 impl Default for CSharpCodeGenOptions {
@@ -14,59 +89,397 @@ impl Default for CSharpCodeGenOptions {
         Self {
             namespace: "MyNamespace".into(),
             with_json_convert_for_union_tags: true,
+            file_per_type: false,
+            with_json_polymorphic: false,
+            json_serializer_context: None,
+            structure_kind: CSharpStructureKind::default(),
+            init_only_setters: false,
+            with_required_attribute: true,
+            language_version: 11,
+            json_library: CSharpJsonLibrary::default(),
+            with_equality: false,
         }
     }
 }
 
-pub fn generate_csharp(opts: &CodeGenOptions, root_block: &Block) -> String {
-    let mut writer = IndentedWriter::default();
+impl CSharpCodeGenOptions {
+    /// `required` members and static abstract interface members.
+    fn supports_required_members(&self) -> bool {
+        self.language_version >= 11
+    }
 
-    writer.write_string_line("using NeatProto;");
-    writer.next_line();
-    writer.write_string_line(format!("namespace {};", opts.csharp.namespace));
-    writer.next_line();
+    /// File scoped namespaces and record structs.
+    fn supports_record_structs(&self) -> bool {
+        self.language_version >= 10
+    }
+}
+
+pub struct CSharpCodeGenerator;
+
+impl CodeGenerator for CSharpCodeGenerator {
+    fn file_extension(&self) -> &str {
+        "cs"
+    }
+
+    /// `[JsonPolymorphic]` writes the type discriminator next to other properties, so it can
+    /// only represent internally tagged unions.
+    fn tagged_union_reprs(&self, opts: &CodeGenOptions) -> &'static [TaggedUnionRepr] {
+        if opts.csharp.with_json_polymorphic {
+            &[TaggedUnionRepr::Internal]
+        } else {
+            &[TaggedUnionRepr::Internal, TaggedUnionRepr::Adjacent]
+        }
+    }
+
+    fn validate_options(&self, opts: &CodeGenOptions) -> Result<(), CodeGenError> {
+        if opts.csharp.language_version < 9 {
+            return Err(CodeGenError::InvalidOption(
+                "language_version".to_string(),
+                format!(
+                    "C# {} is not supported, use 9 or newer",
+                    opts.csharp.language_version
+                ),
+            ));
+        }
+
+        // Records need `IsExternalInit`, which is missing in runtimes older than .NET 5 that
+        // C# 9 is usually used with (e.g. Unity).
+        if matches!(
+            opts.csharp.structure_kind,
+            CSharpStructureKind::RecordClass | CSharpStructureKind::RecordStruct
+        ) && !opts.csharp.supports_record_structs()
+        {
+            return Err(CodeGenError::InvalidOption(
+                "structure_kind".to_string(),
+                "records require C# 10".to_string(),
+            ));
+        }
+
+        // `[JsonPolymorphic]` comes with .NET 7, which defaults to C# 11.
+        if opts.csharp.with_json_polymorphic && !opts.csharp.supports_required_members() {
+            return Err(CodeGenError::InvalidOption(
+                "with_json_polymorphic".to_string(),
+                "`[JsonPolymorphic]` requires C# 11".to_string(),
+            ));
+        }
+
+        if opts.csharp.json_library == CSharpJsonLibrary::NewtonsoftJson {
+            if opts.csharp.with_json_polymorphic {
+                return Err(CodeGenError::InvalidOption(
+                    "with_json_polymorphic".to_string(),
+                    "`[JsonPolymorphic]` is only available in System.Text.Json".to_string(),
+                ));
+            }
+
+            if opts.csharp.json_serializer_context.is_some() {
+                return Err(CodeGenError::InvalidOption(
+                    "json_serializer_context".to_string(),
+                    "source generation is only available in System.Text.Json".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_header(&self, opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+        writer.write_string_line("using NeatProto;");
+        writer.next_line();
+
+        if opts.csharp.supports_record_structs() {
+            writer.write_string_line(format!("namespace {};", opts.csharp.namespace));
+            writer.next_line();
+        } else {
+            writer.write_string_line(format!("namespace {}", opts.csharp.namespace));
+            writer.write_line("{");
+            writer.push_indent();
+        }
+    }
+
+    fn write_footer(&self, opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+        if !opts.csharp.supports_record_structs() {
+            writer.pop_indent();
+            writer.write_line("}");
+        }
+    }
+
+    fn visit_nested_block(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        block: &Block,
+    ) -> Result<(), CodeGenError> {
+        writer.write_indented_line("{");
+        writer.push_indent();
+        self.visit_block(opts, writer, block)?;
+        writer.pop_indent();
+        writer.write_indented_line("}");
+        Ok(())
+    }
+
+    fn visit_structure(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        structure: &Structure,
+    ) -> Result<(), CodeGenError> {
+        write_structure(opts, writer, structure);
+        Ok(())
+    }
+
+    fn visit_enum(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        e: &Enum,
+    ) -> Result<(), CodeGenError> {
+        write_enum(opts, writer, e)
+    }
+
+    fn visit_alias(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        alias: &Alias,
+    ) -> Result<(), CodeGenError> {
+        write_alias(opts, writer, alias);
+        Ok(())
+    }
+
+    fn generate(&self, opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+        self.validate(opts, root_block)?;
+        self.generate_code(opts, root_block, true)
+    }
+
+    fn generate_files(
+        &self,
+        opts: &CodeGenOptions,
+        root_block: &Block,
+        file_path: &Path,
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        self.validate(opts, root_block)?;
+        self.generate_block_files(opts, root_block, file_path, true)
+    }
+
+    /// When generating a serializer context, a single context is generated for types of all
+    /// modules into the root of the output directory.
+    fn generate_modules(
+        &self,
+        opts: &CodeGenOptions,
+        modules: &[(PathBuf, Block)],
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        let mut files = BTreeMap::new();
+        for (file_path, block) in modules {
+            self.validate(opts, block)?;
+            files.extend(self.generate_block_files(opts, block, file_path, false)?);
+        }
+
+        if let Some(context_name) = &opts.csharp.json_serializer_context {
+            let blocks = modules.iter().map(|(_, block)| block).collect::<Vec<_>>();
+            files.insert(
+                PathBuf::from(format!("{}.{}", context_name, self.file_extension())),
+                self.generate_json_serializer_context_file(opts, context_name, &blocks),
+            );
+        }
+
+        Ok(files)
+    }
+}
+
+impl CSharpCodeGenerator {
+    fn generate_code(
+        &self,
+        opts: &CodeGenOptions,
+        block: &Block,
+        with_context: bool,
+    ) -> Result<String, CodeGenError> {
+        let mut writer = IndentedWriter::default();
+        self.write_header(opts, &mut writer);
+        self.visit_block(opts, &mut writer, block)?;
+
+        if with_context && let Some(context_name) = &opts.csharp.json_serializer_context {
+            write_json_serializer_context(opts, &mut writer, context_name, &[block]);
+        }
+
+        self.write_footer(opts, &mut writer);
+        Ok(writer.into_string())
+    }
+
+    /// Generates code of a block which was already validated.
+    fn generate_block_files(
+        &self,
+        opts: &CodeGenOptions,
+        block: &Block,
+        file_path: &Path,
+        with_context: bool,
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        if !opts.csharp.file_per_type {
+            return Ok(BTreeMap::from([(
+                file_path.to_path_buf(),
+                self.generate_code(opts, block, with_context)?,
+            )]));
+        }
+
+        let mut files = BTreeMap::new();
+        self.generate_type_files(opts, block, file_path, &mut files)?;
+
+        if with_context && let Some(context_name) = &opts.csharp.json_serializer_context {
+            files.insert(
+                file_path.with_file_name(format!("{}.{}", context_name, self.file_extension())),
+                self.generate_json_serializer_context_file(opts, context_name, &[block]),
+            );
+        }
+
+        Ok(files)
+    }
 
-    write_block(opts, &mut writer, root_block, false);
-    writer.to_string()
+    fn generate_json_serializer_context_file(
+        &self,
+        opts: &CodeGenOptions,
+        context_name: &str,
+        blocks: &[&Block],
+    ) -> String {
+        let mut writer = IndentedWriter::default();
+        self.write_header(opts, &mut writer);
+        write_json_serializer_context(opts, &mut writer, context_name, blocks);
+        self.write_footer(opts, &mut writer);
+        writer.into_string()
+    }
+
+    fn generate_type_files(
+        &self,
+        opts: &CodeGenOptions,
+        block: &Block,
+        file_path: &Path,
+        files: &mut BTreeMap<PathBuf, String>,
+    ) -> Result<(), CodeGenError> {
+        for node in &block.nodes {
+            let mut writer = IndentedWriter::default();
+            self.write_header(opts, &mut writer);
+
+            let type_name = match node {
+                BlockNode::Block(block) => {
+                    self.generate_type_files(opts, block, file_path, files)?;
+                    continue;
+                }
+                BlockNode::Structure(structure) => {
+                    self.visit_structure(opts, &mut writer, structure)?;
+                    &structure.name
+                }
+                BlockNode::Enum(e) => {
+                    self.visit_enum(opts, &mut writer, e)?;
+                    &e.name
+                }
+                BlockNode::Alias(alias) => {
+                    self.visit_alias(opts, &mut writer, alias)?;
+                    &alias.alias_name
+                }
+            };
+
+            self.write_footer(opts, &mut writer);
+
+            let type_file_path = file_path.with_file_name(format!(
+                "{}.{}",
+                type_name.to_name_case(opts.type_name_case),
+                self.file_extension()
+            ));
+            files.insert(type_file_path, writer.into_string());
+        }
+
+        Ok(())
+    }
+}
+
+pub fn generate_csharp(opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+    CSharpCodeGenerator.generate(opts, root_block)
 }
 
-fn write_block(
+/// Writes a `JsonSerializerContext` with metadata of every type declared in given blocks.
+fn write_json_serializer_context(
     opts: &CodeGenOptions,
     writer: &mut IndentedWriter,
-    block: &Block,
-    with_brackets: bool,
+    context_name: &str,
+    blocks: &[&Block],
 ) {
-    if with_brackets {
-        writer.write_indented_line("{");
-        writer.push_indent();
+    let mut types = vec![];
+    for block in blocks {
+        collect_serializable_types(opts, block, &mut types);
     }
 
-    for node in &block.nodes {
-        match node {
-            BlockNode::Block(block) => write_block(opts, writer, block, true),
-            BlockNode::Structure(structure) => write_structure(opts, writer, structure),
-            BlockNode::Alias(alias) => write_alias(opts, writer, alias),
-            BlockNode::Enum(e) => write_enum(opts, writer, e),
+    for (type_name, type_info_property_name) in types {
+        match type_info_property_name {
+            Some(property_name) => writer.write_indented_line(format!(
+                "[global::System.Text.Json.Serialization.JsonSerializable(typeof({type_name}), \
+                 TypeInfoPropertyName = \"{property_name}\")]"
+            )),
+            None => writer.write_indented_line(format!(
+                "[global::System.Text.Json.Serialization.JsonSerializable(typeof({type_name}))]"
+            )),
         }
     }
 
-    if with_brackets {
-        writer.pop_indent();
-        writer.write_indented_line("}");
+    writer.write_indented_line(format!(
+        "public partial class {context_name} : \
+         global::System.Text.Json.Serialization.JsonSerializerContext"
+    ));
+    writer.write_indented_line("{");
+    writer.write_indented_line("}");
+}
+
+/// Collects names of all generated types along with names of properties holding their
+/// metadata in the serializer context. Cases of tagged unions are nested classes, which
+/// could clash with other types of the same name, so they are prefixed with the union name.
+fn collect_serializable_types(
+    opts: &CodeGenOptions,
+    block: &Block,
+    types: &mut Vec<(String, Option<String>)>,
+) {
+    for node in &block.nodes {
+        match node {
+            BlockNode::Block(block) => collect_serializable_types(opts, block, types),
+            BlockNode::Structure(structure) => {
+                types.push((structure.name.to_name_case(opts.type_name_case), None))
+            }
+            BlockNode::Alias(alias) => {
+                types.push((alias.alias_name.to_name_case(opts.type_name_case), None))
+            }
+            BlockNode::Enum(e) => {
+                let enum_class_name = e.name.to_name_case(opts.type_name_case);
+                let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
+                types.push((enum_class_name.clone(), None));
+
+                if is_tagged_union {
+                    for item in &e.items {
+                        let item_name = item.name.to_name_case(opts.enum_item_name_case);
+                        types.push((
+                            format!("{enum_class_name}.{item_name}"),
+                            Some(format!("{enum_class_name}{item_name}")),
+                        ));
+                    }
+                    types.push((format!("{enum_class_name}Type"), None));
+                }
+            }
+        }
     }
 }
 
 fn write_structure(opts: &CodeGenOptions, writer: &mut IndentedWriter, structure: &Structure) {
+    let structure_kind = opts.csharp.structure_kind;
+    let class_name = structure.name.to_name_case(opts.type_name_case);
     writer.write_indented(format!(
-        "public class {}",
-        structure.name.to_name_case(opts.type_name_case)
+        "public {} {}",
+        structure_kind.keyword(),
+        class_name
     ));
 
     writer.next_line();
     writer.write_indented_line("{");
     writer.push_indent();
 
-    write_structure_body(opts, writer, structure, true);
+    let is_readonly = structure_kind == CSharpStructureKind::ReadonlyStruct;
+    write_structure_body(opts, writer, structure, true, is_readonly);
+    write_constructor(opts, writer, &class_name, structure);
+    write_equality_members(opts, writer, &class_name, structure_kind, &structure.fields);
 
     writer.pop_indent();
     writer.write_indented("}");
@@ -78,49 +491,284 @@ fn write_structure_body(
     writer: &mut IndentedWriter,
     structure: &Structure,
     with_access_modifiers: bool,
+    is_readonly: bool,
 ) {
+    // Without `required` members, properties are initialized by a constructor, so they do
+    // not need `init` accessors, which are not available in older runtimes.
+    let accessors = match is_readonly || opts.csharp.init_only_setters {
+        true if !opts.csharp.supports_required_members() => "get;",
+        true => "get; init;",
+        false => "get; set;",
+    };
+    let modifiers = if opts.csharp.supports_required_members() {
+        "public required "
+    } else {
+        "public "
+    };
+
     for field in &structure.fields {
-        writer.write_indented_line("[global::System.ComponentModel.DataAnnotations.Required]");
+        if opts.csharp.with_required_attribute {
+            writer.write_indented_line("[global::System.ComponentModel.DataAnnotations.Required]");
+        }
         writer.write_indent();
         if with_access_modifiers {
-            writer.write("public required ");
+            writer.write(modifiers);
         }
 
         let field_full_type_name = get_full_type_name(opts, &field.type_name);
         let field_name = field.name.to_name_case(opts.field_name_case);
 
         writer.write_string_line(format!(
-            "{field_full_type_name} {field_name} {{ get; set; }}"
+            "{field_full_type_name} {field_name} {{ {accessors} }}"
+        ));
+    }
+}
+
+/// Writes a constructor initializing every property, used instead of `required` properties
+/// before C# 11.
+fn write_constructor(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    class_name: &str,
+    structure: &Structure,
+) {
+    if opts.csharp.supports_required_members() || structure.fields.is_empty() {
+        return;
+    }
+
+    let field_names = structure
+        .fields
+        .iter()
+        .map(|field| field.name.to_name_case(opts.field_name_case))
+        .collect::<Vec<_>>();
+    let parameters = structure
+        .fields
+        .iter()
+        .zip(&field_names)
+        .map(|(field, field_name)| {
+            format!(
+                "{} {}",
+                get_full_type_name(opts, &field.type_name),
+                field_name
+            )
+        })
+        .collect::<Vec<_>>();
+
+    writer.next_line();
+    writer.write_indented_line(format!("public {}({})", class_name, parameters.join(", ")));
+    writer.write_indented_line("{");
+    writer.push_indent();
+
+    for field_name in &field_names {
+        writer.write_indented_line(format!("this.{field_name} = {field_name};"));
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+}
+
+/// Writes members comparing, hashing and printing a type by its fields. Records get only the
+/// members which differ from the ones synthesized by the compiler.
+fn write_equality_members(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    class_name: &str,
+    structure_kind: CSharpStructureKind,
+    fields: &[StructureField],
+) {
+    if !opts.csharp.with_equality {
+        return;
+    }
+
+    let is_record = matches!(
+        structure_kind,
+        CSharpStructureKind::RecordClass | CSharpStructureKind::RecordStruct
+    );
+    let is_struct = matches!(
+        structure_kind,
+        CSharpStructureKind::RecordStruct | CSharpStructureKind::ReadonlyStruct
+    );
+
+    writer.next_line();
+    writer.write_indented_line("#nullable enable");
+
+    // --
+    // Equals
+    let mut conditions = Vec::new();
+    if !is_struct {
+        conditions.push("other is not null".to_string());
+    }
+
+    for field in fields {
+        let field_name = field.name.to_name_case(opts.field_name_case);
+        if field.type_name.is_array {
+            conditions.push(format!(
+                "(this.{field_name} == other.{field_name} || this.{field_name} is not null \
+                 && other.{field_name} is not null \
+                 && global::System.Linq.Enumerable.SequenceEqual(\
+                 this.{field_name}, other.{field_name}))"
+            ));
+        } else {
+            conditions.push(format!(
+                "global::System.Collections.Generic.EqualityComparer<{}>.Default.Equals(\
+                 this.{field_name}, other.{field_name})",
+                get_full_type_name(opts, &field.type_name)
+            ));
+        }
+    }
+
+    if conditions.is_empty() {
+        conditions.push("true".to_string());
+    }
+
+    let equals_signature = match structure_kind {
+        CSharpStructureKind::RecordClass => {
+            format!("public virtual bool Equals({class_name}? other)")
+        }
+        _ if is_struct => format!("public bool Equals({class_name} other)"),
+        _ => format!("public bool Equals({class_name}? other)"),
+    };
+
+    writer.write_indented_line(equals_signature);
+    writer.write_indented_line("{");
+    writer.push_indent();
+    for (i, condition) in conditions.iter().enumerate() {
+        let prefix = if i == 0 { "return " } else { "    && " };
+        let suffix = if i == conditions.len() - 1 { ";" } else { "" };
+        writer.write_indented_line(format!("{prefix}{condition}{suffix}"));
+    }
+    writer.pop_indent();
+    writer.write_indented_line("}");
+
+    if !is_record {
+        writer.next_line();
+        writer.write_indented_line(format!(
+            "public override bool Equals(object? obj) => \
+             obj is {class_name} other && Equals(other);"
+        ));
+    }
+
+    // --
+    // GetHashCode
+    writer.next_line();
+    writer.write_indented_line("public override int GetHashCode()");
+    writer.write_indented_line("{");
+    writer.push_indent();
+    writer.write_indented_line("var hash = new global::System.HashCode();");
+    for field in fields {
+        let field_name = field.name.to_name_case(opts.field_name_case);
+        if field.type_name.is_array {
+            writer.write_indented_line(format!("if (this.{field_name} is not null)"));
+            writer.write_indented_line("{");
+            writer.push_indent();
+            writer.write_indented_line(format!("foreach (var item in this.{field_name})"));
+            writer.write_indented_line("    hash.Add(item);");
+            writer.pop_indent();
+            writer.write_indented_line("}");
+        } else {
+            writer.write_indented_line(format!("hash.Add(this.{field_name});"));
+        }
+    }
+    writer.write_indented_line("return hash.ToHashCode();");
+    writer.pop_indent();
+    writer.write_indented_line("}");
+
+    // --
+    // Operators
+    if !is_record {
+        let (parameter_type, comparison) = if is_struct {
+            (class_name.to_string(), "left.Equals(right)")
+        } else {
+            (
+                format!("{class_name}?"),
+                "global::System.Object.Equals(left, right)",
+            )
+        };
+
+        writer.next_line();
+        writer.write_indented_line(format!(
+            "public static bool operator ==({parameter_type} left, {parameter_type} right) \
+             => {comparison};"
+        ));
+        writer.write_indented_line(format!(
+            "public static bool operator !=({parameter_type} left, {parameter_type} right) \
+             => !(left == right);"
         ));
     }
+
+    // --
+    // ToString
+    let mut to_string = format!("\"{class_name}\"");
+    if !fields.is_empty() {
+        let values = fields
+            .iter()
+            .map(|field| {
+                let field_name = field.name.to_name_case(opts.field_name_case);
+                if field.type_name.is_array {
+                    format!(
+                        "{field_name} = {{(this.{field_name} is null ? \"null\" \
+                         : \"[\" + string.Join(\", \", this.{field_name}) + \"]\")}}"
+                    )
+                } else {
+                    format!("{field_name} = {{this.{field_name}}}")
+                }
+            })
+            .collect::<Vec<_>>();
+        to_string = format!("$\"{class_name} {{{{ {} }}}}\"", values.join(", "));
+    }
+
+    writer.next_line();
+    writer.write_indented_line(format!("public override string ToString() => {to_string};"));
+    writer.write_indented_line("#nullable restore");
 }
 
 fn write_alias(opts: &CodeGenOptions, writer: &mut IndentedWriter, alias: &Alias) {
     let alias_name = alias.alias_name.to_name_case(opts.type_name_case);
     let aliased_type_name = translate_type_name(opts, &alias.aliased_type_name);
 
-    writer.write_indented_line(format!(
-        "[global::System.Text.Json.Serialization.JsonConverter(typeof({}JsonConverter))]",
-        alias_name
-    ));
-    writer.write_line(
+    writer.write_indented_line(
+        opts.csharp
+            .json_library
+            .converter_attribute(&format!("{alias_name}JsonConverter")),
+    );
+
+    if opts.csharp.supports_record_structs() {
+        writer.write_indented_line(format!(
+            "public record struct {alias_name}({aliased_type_name} Value)"
+        ));
+        writer.write_indented_line("{");
+    } else {
+        writer.write_indented_lines(
+            format!(
+                r#"
+public readonly struct {alias_name}
+{{
+    public {alias_name}({aliased_type_name} value) => Value = value;
+
+    public {aliased_type_name} Value {{ get; }}
+"#
+            )
+            .trim_start(),
+        );
+        writer.next_line();
+    }
+
+    writer.write_indented_lines(
         format!(
             r#"
-public record struct {alias_name}({aliased_type_name} Value)
-{{
     public override string ToString() => Value.ToString();
 
     public static implicit operator {aliased_type_name}({alias_name} alias) => alias.Value;
     public static implicit operator {alias_name}({aliased_type_name} alias) => new(alias);
-}}
-    "#
+}}"#
         )
-        .trim(),
+        .trim_start_matches('\n'),
     );
 
-    writer.write_indented(
-        format!(
-            r#"
+    match opts.csharp.json_library {
+        CSharpJsonLibrary::SystemTextJson => writer.write_indented_lines(
+            format!(
+                r#"
 public class {alias_name}JsonConverter : global::System.Text.Json.Serialization.JsonConverter<{alias_name}>
 {{
     public override void Write(
@@ -139,17 +787,48 @@ public class {alias_name}JsonConverter : global::System.Text.Json.Serialization.
         reader.Get(out {aliased_type_name} value);
         return value;
     }}
-}}"#) .trim()
-    );
+}}"#
+            )
+            .trim_start_matches('\n'),
+        ),
+        CSharpJsonLibrary::NewtonsoftJson => writer.write_indented_lines(
+            format!(
+                r#"
+public class {alias_name}JsonConverter : global::Newtonsoft.Json.JsonConverter<{alias_name}>
+{{
+    public override void WriteJson(
+        global::Newtonsoft.Json.JsonWriter writer,
+        {alias_name} aliasType,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {{
+        global::NeatProto.Newtonsoft.JsonValues.Write(writer, aliasType.Value, serializer);
+    }}
 
-    writer.next_line();
+    public override {alias_name} ReadJson(
+        global::Newtonsoft.Json.JsonReader reader,
+        global::System.Type objectType,
+        {alias_name} existingValue,
+        bool hasExistingValue,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {{
+        global::NeatProto.Newtonsoft.JsonValues.Read(reader, serializer, out {aliased_type_name} value);
+        return value;
+    }}
+}}"#
+            )
+            .trim_start_matches('\n'),
+        ),
+    }
 }
 
-fn write_enum(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
+fn write_enum(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
     let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
     if is_tagged_union {
-        write_tagged_union(opts, writer, e);
-        return;
+        return write_tagged_union(opts, writer, e);
     }
 
     writer.write_indented_line(format!(
@@ -170,28 +849,60 @@ fn write_enum(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
 
     writer.pop_indent();
     writer.write_indented_line("}");
+    Ok(())
 }
 
-fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
+fn write_tagged_union(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
     let enum_class_name = e.name.to_name_case(opts.type_name_case);
+    let layout = TaggedUnionLayout::resolve(opts, e)?;
     let discriminator_class_name = format!("{}Type", enum_class_name);
 
     // --
     // Generate a separate class for each item, but put all of them inside an abstract class,
     // which basically acts as a namespace.
+    if opts.csharp.with_json_polymorphic {
+        write_json_polymorphic_attrs(opts, writer, e, &layout);
+    } else {
+        writer.write_indented_line(
+            opts.csharp
+                .json_library
+                .converter_attribute(&format!("{enum_class_name}JsonConverter")),
+        );
+    }
     writer.write_indented_line(format!("public abstract class {}", enum_class_name));
     writer.write_indented_line("{");
     writer.push_indent();
 
     for item in &e.items {
-        writer.write_indented(format!(
-            "public class {} : {}, global::NeatProto.ITaggedUnionCase<{}>",
-            item.name.to_name_case(opts.enum_item_name_case),
-            enum_class_name,
-            discriminator_class_name
-        ));
+        let item_class_name = item.name.to_name_case(opts.enum_item_name_case);
+
+        // Newtonsoft.Json looks up converters of base classes too, so cases need to opt out of
+        // the converter of the union.
+        if opts.csharp.json_library == CSharpJsonLibrary::NewtonsoftJson {
+            writer.write_indented_line(
+                opts.csharp
+                    .json_library
+                    .converter_attribute("global::NeatProto.Newtonsoft.DefaultJsonConverter"),
+            );
+        }
+
+        // `ITaggedUnionCase` declares `Kind` as a static abstract member.
+        if opts.csharp.supports_required_members() {
+            writer.write_indented_line(format!(
+                "public class {} : {}, global::NeatProto.ITaggedUnionCase<{}>",
+                item_class_name, enum_class_name, discriminator_class_name
+            ));
+        } else {
+            writer.write_indented_line(format!(
+                "public class {} : {}",
+                item_class_name, enum_class_name
+            ));
+        }
 
-        writer.next_line();
         writer.write_indented_line("{");
         writer.push_indent();
 
@@ -203,9 +914,20 @@ fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &En
         ));
 
         if let Some(structure) = &item.structure {
-            write_structure_body(opts, writer, structure, true);
+            write_structure_body(opts, writer, structure, true, false);
+            write_constructor(opts, writer, &item_class_name, structure);
         }
 
+        write_equality_members(
+            opts,
+            writer,
+            &item_class_name,
+            CSharpStructureKind::Class,
+            item.structure
+                .as_ref()
+                .map_or(&[], |structure| &structure.fields),
+        );
+
         writer.pop_indent();
         writer.write_indented_line("}");
     }
@@ -214,28 +936,49 @@ fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &En
     writer.pop_indent();
     writer.write_indented_line("}");
 
+    if !opts.csharp.with_json_polymorphic {
+        write_tagged_union_json_converter(opts, writer, e, &layout);
+    }
+
     // --
     // Generate class representing the discriminator.
     if opts.csharp.with_json_convert_for_union_tags {
+        writer.write_indented_line(
+            opts.csharp
+                .json_library
+                .converter_attribute(&format!("{discriminator_class_name}JsonConverter")),
+        );
+    }
+
+    if opts.csharp.supports_record_structs() {
         writer.write_indented_line(format!(
-            "[global::System.Text.Json.Serialization.JsonConverter(typeof({}JsonConverter))]",
+            "public readonly record struct {}(string Value)",
             discriminator_class_name
         ));
-    }
-
-    writer.write_indented_line(format!(
-        "public readonly record struct {}(string Value)",
-        discriminator_class_name
-    ));
-    writer.write_indented_line("{");
-    writer.push_indent();
+        writer.write_indented_line("{");
+        writer.push_indent();
+    } else {
+        writer.write_indented_line(format!(
+            "public readonly struct {}",
+            discriminator_class_name
+        ));
+        writer.write_indented_line("{");
+        writer.push_indent();
+        writer.write_indented_line(format!(
+            "public {}(string value) => Value = value;",
+            discriminator_class_name
+        ));
+        writer.next_line();
+        writer.write_indented_line("public string Value { get; }");
+        writer.next_line();
+    }
 
     for item in &e.items {
         writer.write_indented_line(format!(
             "public static readonly {} {} = new(\"{}\");",
             discriminator_class_name,
             item.name.to_case(Case::Camel),
-            item.name.to_case(Case::Camel),
+            tag_value(opts, item),
         ));
     }
 
@@ -245,9 +988,23 @@ fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &En
     // --
     // Generate JSON converter for the discriminator struct.
     if opts.csharp.with_json_convert_for_union_tags {
-        writer.write_indented(
-            format!(
-                r#"
+        write_discriminator_json_converter(opts, writer, e, &discriminator_class_name);
+    }
+
+    Ok(())
+}
+
+fn write_discriminator_json_converter(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+    discriminator_class_name: &str,
+) {
+    let exception_class_name = match opts.csharp.json_library {
+        CSharpJsonLibrary::SystemTextJson => {
+            writer.write_indented_lines(
+                format!(
+                    r#"
 public class {discriminator_class_name}JsonConverter : global::System.Text.Json.Serialization.JsonConverter<{discriminator_class_name}>
 {{
     public override void Write(
@@ -269,46 +1026,155 @@ public class {discriminator_class_name}JsonConverter : global::System.Text.Json.
         var value = reader.GetString();
         return value switch
         {{
-    "#)
-            .trim(),
-        );
+    "#
+                )
+                .trim(),
+            );
+            "global::System.Text.Json.JsonException"
+        }
+        CSharpJsonLibrary::NewtonsoftJson => {
+            writer.write_indented_lines(
+                format!(
+                    r#"
+public class {discriminator_class_name}JsonConverter : global::Newtonsoft.Json.JsonConverter<{discriminator_class_name}>
+{{
+    public override void WriteJson(
+        global::Newtonsoft.Json.JsonWriter writer,
+        {discriminator_class_name} messageType,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {{
+        writer.WriteValue(messageType.Value);
+    }}
 
-        writer.next_line();
-        writer.push_indent();
-        writer.push_indent();
-        writer.push_indent();
+    public override {discriminator_class_name} ReadJson(
+        global::Newtonsoft.Json.JsonReader reader,
+        global::System.Type objectType,
+        {discriminator_class_name} existingValue,
+        bool hasExistingValue,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {{
+        if (reader.TokenType != global::Newtonsoft.Json.JsonToken.String)
+            throw new global::Newtonsoft.Json.JsonSerializationException($"Expected string token, but got {{reader.TokenType}}");
 
-        for item in &e.items {
-            writer.write_indented_line(format!(
-                "nameof({}.{}) => {}.{},",
-                discriminator_class_name,
-                item.name.to_case(Case::Camel),
-                discriminator_class_name,
-                item.name.to_case(Case::Camel),
-            ));
+        var value = (string)reader.Value;
+        return value switch
+        {{
+    "#
+                )
+                .trim(),
+            );
+            "global::Newtonsoft.Json.JsonSerializationException"
         }
+    };
 
-        writer.pop_indent();
-        writer.pop_indent();
-        writer.pop_indent();
+    writer.push_indent();
+    writer.push_indent();
+    writer.push_indent();
 
-        writer.write_indented(
-            r#"
-            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
-        };
-    }"#,
-        );
+    for item in &e.items {
+        writer.write_indented_line(format!(
+            "\"{}\" => {}.{},",
+            tag_value(opts, item),
+            discriminator_class_name,
+            item.name.to_case(Case::Camel),
+        ));
+    }
 
-        writer.next_line();
-        writer.pop_indent();
-        writer.write_indented_line("}");
+    writer.write_indented_line(format!(
+        "_ => throw new {exception_class_name}($\"Unknown type: {{value}}\")"
+    ));
+    writer.pop_indent();
+    writer.write_indented_line("};");
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.pop_indent();
+    writer.write_indented_line("}");
+}
+
+/// Writes attributes which make System.Text.Json (de)serialize the abstract class of an
+/// internally tagged union as one of its cases, identified by the tag field.
+fn write_json_polymorphic_attrs(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+    layout: &TaggedUnionLayout,
+) {
+    let enum_class_name = e.name.to_name_case(opts.type_name_case);
+
+    writer.write_indented_line(format!(
+        "[global::System.Text.Json.Serialization.JsonPolymorphic(\
+         TypeDiscriminatorPropertyName = \"{}\")]",
+        layout.tag
+    ));
+
+    for item in &e.items {
+        let item_name = item.name.to_name_case(opts.enum_item_name_case);
+        let tag = tag_value(opts, item);
+        writer.write_indented_line(format!(
+            "[global::System.Text.Json.Serialization.JsonDerivedType(\
+             typeof({enum_class_name}.{item_name}), \"{tag}\")]"
+        ));
+    }
+}
+
+/// Writes a converter (de)serializing the abstract class of a tagged union as one of its
+/// cases, using `TaggedUnionJsonConverter` from the support package (or its Newtonsoft.Json
+/// counterpart).
+fn write_tagged_union_json_converter(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+    layout: &TaggedUnionLayout,
+) {
+    let enum_class_name = e.name.to_name_case(opts.type_name_case);
+    let layout_name = match layout.repr {
+        TaggedUnionRepr::Internal => "Internal",
+        _ => "Adjacent",
+    };
+
+    let base_class_name = match opts.csharp.json_library {
+        CSharpJsonLibrary::SystemTextJson => "global::NeatProto.TaggedUnionJsonConverter",
+        CSharpJsonLibrary::NewtonsoftJson => {
+            "global::NeatProto.Newtonsoft.TaggedUnionJsonConverter"
+        }
+    };
+
+    writer.write_indented_line(format!(
+        "public class {enum_class_name}JsonConverter : {base_class_name}<{enum_class_name}>"
+    ));
+    writer.write_indented_line("{");
+    writer.push_indent();
+    writer.write_indented_line(format!("public {enum_class_name}JsonConverter() : base("));
+    writer.push_indent();
+    writer.write_indented_line(format!(
+        "global::NeatProto.TaggedUnionLayout.{layout_name},"
+    ));
+    writer.write_indented_line(format!("\"{}\",", layout.tag));
+    writer.write_indented(format!("\"{}\"", layout.content));
+
+    for item in &e.items {
+        let item_name = item.name.to_name_case(opts.enum_item_name_case);
+        let tag = tag_value(opts, item);
+        writer.write_line(",");
+        writer.write_indented(format!(
+            "new global::NeatProto.TaggedUnionCase(\"{tag}\", \
+             typeof({enum_class_name}.{item_name}), {})",
+            item.structure.is_some()
+        ));
     }
+
+    writer.write_line(")");
+    writer.pop_indent();
+    writer.write_indented_line("{");
+    writer.write_indented_line("}");
+    writer.pop_indent();
+    writer.write_indented_line("}");
 }
 
 fn get_full_type_name(opts: &CodeGenOptions, type_name: &TypeName) -> String {
     let name = translate_type_name(opts, &type_name.token.value());
     if type_name.is_array {
-        if let Some(_) = &type_name.array_size {
+        if type_name.array_size.is_some() {
             return format!("{}[]", name);
         }
         return format!("global::System.Collections.Generic.List<{}>", name);
@@ -340,3 +1206,1604 @@ fn translate_builtin_type_name(type_name: BuiltinTypeName) -> &'static str {
         BuiltinTypeName::String => "string",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::parse;
+
+    fn generate(opts: &CodeGenOptions, source: &str) -> String {
+        generate_csharp(opts, &parse(source)).expect("failed to generate code")
+    }
+
+    fn generate_files(opts: &CodeGenOptions, source: &str) -> BTreeMap<String, String> {
+        CSharpCodeGenerator
+            .generate_files(opts, &parse(source), Path::new("Types.cs"))
+            .expect("failed to generate code")
+            .into_iter()
+            .map(|(path, code)| (path.display().to_string(), code))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_file() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                r#"alias Id = uint64;
+struct Player { id: Id; name: string; }
+enum Color { Red, Green = 5 }
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonConverter(typeof(IdJsonConverter))]
+public record struct Id(ulong Value)
+{
+    public override string ToString() => Value.ToString();
+
+    public static implicit operator ulong(Id alias) => alias.Value;
+    public static implicit operator Id(ulong alias) => new(alias);
+}
+public class IdJsonConverter : global::System.Text.Json.Serialization.JsonConverter<Id>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        Id aliasType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteValue(aliasType.Value);
+    }
+
+    public override Id Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        reader.Get(out ulong value);
+        return value;
+    }
+}
+public class Player
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required Id id { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required string name { get; set; }
+}
+public enum Color {
+    Red,
+    Green = 5,
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Adjacent,
+        "kind",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_file_per_type() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                file_per_type: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let files = generate_files(
+            &opts,
+            r#"alias Id = uint64;
+struct Player { id: Id; name: string; }
+enum Color { Red, Green = 5 }
+enum Shape { Circle { r: float; }, Empty }"#,
+        );
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            ["Color.cs", "Id.cs", "Player.cs", "Shape.cs"]
+        );
+        assert_eq!(
+            files["Color.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public enum Color {
+    Red,
+    Green = 5,
+}
+"##
+        );
+        assert_eq!(
+            files["Id.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonConverter(typeof(IdJsonConverter))]
+public record struct Id(ulong Value)
+{
+    public override string ToString() => Value.ToString();
+
+    public static implicit operator ulong(Id alias) => alias.Value;
+    public static implicit operator Id(ulong alias) => new(alias);
+}
+public class IdJsonConverter : global::System.Text.Json.Serialization.JsonConverter<Id>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        Id aliasType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteValue(aliasType.Value);
+    }
+
+    public override Id Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        reader.Get(out ulong value);
+        return value;
+    }
+}
+"##
+        );
+        assert_eq!(
+            files["Player.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public class Player
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required Id id { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required string name { get; set; }
+}
+"##
+        );
+        assert_eq!(
+            files["Shape.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Adjacent,
+        "kind",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_json_polymorphic() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Internal,
+            csharp: CSharpCodeGenOptions {
+                with_json_polymorphic: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Point { x: float; y: float; }
+@tag("type")
+enum Shape { Circle { center: Point; r: float; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public class Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float y { get; set; }
+}
+[global::System.Text.Json.Serialization.JsonPolymorphic(TypeDiscriminatorPropertyName = "type")]
+[global::System.Text.Json.Serialization.JsonDerivedType(typeof(Shape.Circle), "Circle")]
+[global::System.Text.Json.Serialization.JsonDerivedType(typeof(Shape.Empty), "Empty")]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required Point center { get; set; }
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_converter() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                r#"@repr(internal) @tag("type")
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Internal,
+        "type",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_json_polymorphic_adjacent_union() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Internal,
+            csharp: CSharpCodeGenOptions {
+                with_json_polymorphic: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let block = parse("@repr(adjacent) enum Shape { Circle { r: float; }, Empty }");
+        assert!(matches!(
+            generate_csharp(&opts, &block),
+            Err(CodeGenError::InvalidAnnotation(..))
+        ));
+    }
+
+    #[test]
+    fn test_json_serializer_context() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                json_serializer_context: Some("AppJsonContext".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"alias Id = uint64;
+struct Player { id: Id; tags: string[]; }
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonConverter(typeof(IdJsonConverter))]
+public record struct Id(ulong Value)
+{
+    public override string ToString() => Value.ToString();
+
+    public static implicit operator ulong(Id alias) => alias.Value;
+    public static implicit operator Id(ulong alias) => new(alias);
+}
+public class IdJsonConverter : global::System.Text.Json.Serialization.JsonConverter<Id>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        Id aliasType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteValue(aliasType.Value);
+    }
+
+    public override Id Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        reader.Get(out ulong value);
+        return value;
+    }
+}
+public class Player
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required Id id { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; set; }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Adjacent,
+        "kind",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Id))]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Player))]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Shape))]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Shape.Circle), TypeInfoPropertyName = "ShapeCircle")]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Shape.Empty), TypeInfoPropertyName = "ShapeEmpty")]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(ShapeType))]
+public partial class AppJsonContext : global::System.Text.Json.Serialization.JsonSerializerContext
+{
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_json_serializer_context_file_per_type() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                json_serializer_context: Some("AppJsonContext".to_string()),
+                file_per_type: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let files = generate_files(
+            &opts,
+            r#"struct Player { name: string; }
+enum Color { Red, Green }"#,
+        );
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            ["AppJsonContext.cs", "Color.cs", "Player.cs"]
+        );
+        assert_eq!(
+            files["AppJsonContext.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Player))]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(Color))]
+public partial class AppJsonContext : global::System.Text.Json.Serialization.JsonSerializerContext
+{
+}
+"##
+        );
+        assert_eq!(
+            files["Color.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public enum Color {
+    Red,
+    Green,
+}
+"##
+        );
+        assert_eq!(
+            files["Player.cs"],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public class Player
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required string name { get; set; }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_json_serializer_context_modules() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                json_serializer_context: Some("AppJsonContext".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let modules = [
+            (PathBuf::from("a.cs"), parse("struct A { x: bool; }")),
+            (PathBuf::from("b.cs"), parse("enum B { C { a: A; }, D }")),
+        ];
+        let files = CSharpCodeGenerator
+            .generate_modules(&opts, &modules)
+            .expect("failed to generate code");
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            ["AppJsonContext.cs", "a.cs", "b.cs"]
+        );
+        assert!(!files[Path::new("a.cs")].contains("JsonSerializerContext"));
+        assert_eq!(
+            files[Path::new("AppJsonContext.cs")],
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(A))]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(B))]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(B.C), TypeInfoPropertyName = "BC")]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(B.D), TypeInfoPropertyName = "BD")]
+[global::System.Text.Json.Serialization.JsonSerializable(typeof(BType))]
+public partial class AppJsonContext : global::System.Text.Json.Serialization.JsonSerializerContext
+{
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_structure_kind_record_class() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                structure_kind: CSharpStructureKind::RecordClass,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; tags: string[]; }"),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public record class Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; set; }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_structure_kind_record_struct() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                structure_kind: CSharpStructureKind::RecordStruct,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; tags: string[]; }"),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public record struct Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; set; }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_structure_kind_readonly_struct() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                structure_kind: CSharpStructureKind::ReadonlyStruct,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; tags: string[]; }"),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public readonly struct Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; init; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; init; }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_init_only_setters() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                init_only_setters: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Point { x: float; }
+enum Shape { Circle { center: Point; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public class Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; init; }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required Point center { get; init; }
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Adjacent,
+        "kind",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_language_version_9() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                language_version: 9,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"alias Id = uint64;
+struct Point { id: Id; x: float; }
+enum Shape { Circle { center: Point; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace
+{
+    [global::System.Text.Json.Serialization.JsonConverter(typeof(IdJsonConverter))]
+    public readonly struct Id
+    {
+        public Id(ulong value) => Value = value;
+
+        public ulong Value { get; }
+
+        public override string ToString() => Value.ToString();
+
+        public static implicit operator ulong(Id alias) => alias.Value;
+        public static implicit operator Id(ulong alias) => new(alias);
+    }
+    public class IdJsonConverter : global::System.Text.Json.Serialization.JsonConverter<Id>
+    {
+        public override void Write(
+            global::System.Text.Json.Utf8JsonWriter writer,
+            Id aliasType,
+            global::System.Text.Json.JsonSerializerOptions options)
+        {
+            writer.WriteValue(aliasType.Value);
+        }
+
+        public override Id Read(
+            ref global::System.Text.Json.Utf8JsonReader reader,
+            global::System.Type typeToConvert,
+            global::System.Text.Json.JsonSerializerOptions options)
+        {
+            reader.Get(out ulong value);
+            return value;
+        }
+    }
+    public class Point
+    {
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public Id id { get; set; }
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public float x { get; set; }
+
+        public Point(Id id, float x)
+        {
+            this.id = id;
+            this.x = x;
+        }
+    }
+    [global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+    public abstract class Shape
+    {
+        public class Circle : Shape
+        {
+            public static ShapeType Kind => ShapeType.circle;
+            [global::System.ComponentModel.DataAnnotations.Required]
+            public Point center { get; set; }
+
+            public Circle(Point center)
+            {
+                this.center = center;
+            }
+        }
+        public class Empty : Shape
+        {
+            public static ShapeType Kind => ShapeType.empty;
+        }
+
+    }
+    public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+    {
+        public ShapeJsonConverter() : base(
+            global::NeatProto.TaggedUnionLayout.Adjacent,
+            "kind",
+            "value",
+            new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+            new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+        {
+        }
+    }
+    [global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+    public readonly struct ShapeType
+    {
+        public ShapeType(string value) => Value = value;
+
+        public string Value { get; }
+
+        public static readonly ShapeType circle = new("Circle");
+        public static readonly ShapeType empty = new("Empty");
+    }
+    public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+    {
+        public override void Write(
+            global::System.Text.Json.Utf8JsonWriter writer,
+            ShapeType messageType,
+            global::System.Text.Json.JsonSerializerOptions options)
+        {
+            writer.WriteStringValue(messageType.Value);
+        }
+
+        public override ShapeType Read(
+            ref global::System.Text.Json.Utf8JsonReader reader,
+            global::System.Type typeToConvert,
+            global::System.Text.Json.JsonSerializerOptions options)
+        {
+            if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+                throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+            var value = reader.GetString();
+            return value switch
+            {
+                "Circle" => ShapeType.circle,
+                "Empty" => ShapeType.empty,
+                _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+            };
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_language_version_10() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                language_version: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"alias Id = uint64;
+struct Point { id: Id; x: float; }
+enum Shape { Circle { center: Point; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::System.Text.Json.Serialization.JsonConverter(typeof(IdJsonConverter))]
+public record struct Id(ulong Value)
+{
+    public override string ToString() => Value.ToString();
+
+    public static implicit operator ulong(Id alias) => alias.Value;
+    public static implicit operator Id(ulong alias) => new(alias);
+}
+public class IdJsonConverter : global::System.Text.Json.Serialization.JsonConverter<Id>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        Id aliasType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteValue(aliasType.Value);
+    }
+
+    public override Id Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        reader.Get(out ulong value);
+        return value;
+    }
+}
+public class Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public Id id { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public float x { get; set; }
+
+    public Point(Id id, float x)
+    {
+        this.id = id;
+        this.x = x;
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public Point center { get; set; }
+
+        public Circle(Point center)
+        {
+            this.center = center;
+        }
+    }
+    public class Empty : Shape
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Adjacent,
+        "kind",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_language_version_9_readonly_struct() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                language_version: 9,
+                structure_kind: CSharpStructureKind::ReadonlyStruct,
+                init_only_setters: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; }"),
+            r##"using NeatProto;
+
+namespace MyNamespace
+{
+    public readonly struct Point
+    {
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public float x { get; }
+
+        public Point(float x)
+        {
+            this.x = x;
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_unsupported_language_version() {
+        for (language_version, structure_kind) in [
+            (8, CSharpStructureKind::Class),
+            (9, CSharpStructureKind::RecordClass),
+            (9, CSharpStructureKind::RecordStruct),
+        ] {
+            let opts = CodeGenOptions {
+                csharp: CSharpCodeGenOptions {
+                    language_version,
+                    structure_kind,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            assert!(matches!(
+                generate_csharp(&opts, &parse("struct A { x: bool; }")),
+                Err(CodeGenError::InvalidOption(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_json_polymorphic_language_version() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                language_version: 10,
+                with_json_polymorphic: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_csharp(&opts, &parse("struct A { x: bool; }")),
+            Err(CodeGenError::InvalidOption(option, _)) if option == "with_json_polymorphic"
+        ));
+    }
+
+    #[test]
+    fn test_newtonsoft_json() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                json_library: CSharpJsonLibrary::NewtonsoftJson,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"alias Id = uint64;
+struct Player { id: Id; }
+@repr(internal) @tag("type")
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+[global::Newtonsoft.Json.JsonConverter(typeof(IdJsonConverter))]
+public record struct Id(ulong Value)
+{
+    public override string ToString() => Value.ToString();
+
+    public static implicit operator ulong(Id alias) => alias.Value;
+    public static implicit operator Id(ulong alias) => new(alias);
+}
+public class IdJsonConverter : global::Newtonsoft.Json.JsonConverter<Id>
+{
+    public override void WriteJson(
+        global::Newtonsoft.Json.JsonWriter writer,
+        Id aliasType,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {
+        global::NeatProto.Newtonsoft.JsonValues.Write(writer, aliasType.Value, serializer);
+    }
+
+    public override Id ReadJson(
+        global::Newtonsoft.Json.JsonReader reader,
+        global::System.Type objectType,
+        Id existingValue,
+        bool hasExistingValue,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {
+        global::NeatProto.Newtonsoft.JsonValues.Read(reader, serializer, out ulong value);
+        return value;
+    }
+}
+public class Player
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required Id id { get; set; }
+}
+[global::Newtonsoft.Json.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    [global::Newtonsoft.Json.JsonConverter(typeof(global::NeatProto.Newtonsoft.DefaultJsonConverter))]
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+    }
+    [global::Newtonsoft.Json.JsonConverter(typeof(global::NeatProto.Newtonsoft.DefaultJsonConverter))]
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.Newtonsoft.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Internal,
+        "type",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::Newtonsoft.Json.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::Newtonsoft.Json.JsonConverter<ShapeType>
+{
+    public override void WriteJson(
+        global::Newtonsoft.Json.JsonWriter writer,
+        ShapeType messageType,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {
+        writer.WriteValue(messageType.Value);
+    }
+
+    public override ShapeType ReadJson(
+        global::Newtonsoft.Json.JsonReader reader,
+        global::System.Type objectType,
+        ShapeType existingValue,
+        bool hasExistingValue,
+        global::Newtonsoft.Json.JsonSerializer serializer)
+    {
+        if (reader.TokenType != global::Newtonsoft.Json.JsonToken.String)
+            throw new global::Newtonsoft.Json.JsonSerializationException($"Expected string token, but got {reader.TokenType}");
+
+        var value = (string)reader.Value;
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::Newtonsoft.Json.JsonSerializationException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_newtonsoft_json_unsupported_options() {
+        for (with_json_polymorphic, json_serializer_context) in
+            [(true, None), (false, Some("AppJsonContext".to_string()))]
+        {
+            let opts = CodeGenOptions {
+                tagged_union_repr: TaggedUnionRepr::Internal,
+                csharp: CSharpCodeGenOptions {
+                    json_library: CSharpJsonLibrary::NewtonsoftJson,
+                    with_json_polymorphic,
+                    json_serializer_context,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            assert!(matches!(
+                generate_csharp(&opts, &parse("struct A { x: bool; }")),
+                Err(CodeGenError::InvalidOption(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_equality() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                with_equality: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Point { x: float; tags: string[]; }
+enum Shape { Circle { center: Point; r: float; }, Empty }"#,
+            ),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public class Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; set; }
+
+    #nullable enable
+    public bool Equals(Point? other)
+    {
+        return other is not null
+            && global::System.Collections.Generic.EqualityComparer<float>.Default.Equals(this.x, other.x)
+            && (this.tags == other.tags || this.tags is not null && other.tags is not null && global::System.Linq.Enumerable.SequenceEqual(this.tags, other.tags));
+    }
+
+    public override bool Equals(object? obj) => obj is Point other && Equals(other);
+
+    public override int GetHashCode()
+    {
+        var hash = new global::System.HashCode();
+        hash.Add(this.x);
+        if (this.tags is not null)
+        {
+            foreach (var item in this.tags)
+                hash.Add(item);
+        }
+        return hash.ToHashCode();
+    }
+
+    public static bool operator ==(Point? left, Point? right) => global::System.Object.Equals(left, right);
+    public static bool operator !=(Point? left, Point? right) => !(left == right);
+
+    public override string ToString() => $"Point {{ x = {this.x}, tags = {(this.tags is null ? "null" : "[" + string.Join(", ", this.tags) + "]")} }}";
+    #nullable restore
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeJsonConverter))]
+public abstract class Shape
+{
+    public class Circle : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.circle;
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required Point center { get; set; }
+        [global::System.ComponentModel.DataAnnotations.Required]
+        public required float r { get; set; }
+
+        #nullable enable
+        public bool Equals(Circle? other)
+        {
+            return other is not null
+                && global::System.Collections.Generic.EqualityComparer<Point>.Default.Equals(this.center, other.center)
+                && global::System.Collections.Generic.EqualityComparer<float>.Default.Equals(this.r, other.r);
+        }
+
+        public override bool Equals(object? obj) => obj is Circle other && Equals(other);
+
+        public override int GetHashCode()
+        {
+            var hash = new global::System.HashCode();
+            hash.Add(this.center);
+            hash.Add(this.r);
+            return hash.ToHashCode();
+        }
+
+        public static bool operator ==(Circle? left, Circle? right) => global::System.Object.Equals(left, right);
+        public static bool operator !=(Circle? left, Circle? right) => !(left == right);
+
+        public override string ToString() => $"Circle {{ center = {this.center}, r = {this.r} }}";
+        #nullable restore
+    }
+    public class Empty : Shape, global::NeatProto.ITaggedUnionCase<ShapeType>
+    {
+        public static ShapeType Kind => ShapeType.empty;
+
+        #nullable enable
+        public bool Equals(Empty? other)
+        {
+            return other is not null;
+        }
+
+        public override bool Equals(object? obj) => obj is Empty other && Equals(other);
+
+        public override int GetHashCode()
+        {
+            var hash = new global::System.HashCode();
+            return hash.ToHashCode();
+        }
+
+        public static bool operator ==(Empty? left, Empty? right) => global::System.Object.Equals(left, right);
+        public static bool operator !=(Empty? left, Empty? right) => !(left == right);
+
+        public override string ToString() => "Empty";
+        #nullable restore
+    }
+
+}
+public class ShapeJsonConverter : global::NeatProto.TaggedUnionJsonConverter<Shape>
+{
+    public ShapeJsonConverter() : base(
+        global::NeatProto.TaggedUnionLayout.Adjacent,
+        "kind",
+        "value",
+        new global::NeatProto.TaggedUnionCase("Circle", typeof(Shape.Circle), true),
+        new global::NeatProto.TaggedUnionCase("Empty", typeof(Shape.Empty), false))
+    {
+    }
+}
+[global::System.Text.Json.Serialization.JsonConverter(typeof(ShapeTypeJsonConverter))]
+public readonly record struct ShapeType(string Value)
+{
+    public static readonly ShapeType circle = new("Circle");
+    public static readonly ShapeType empty = new("Empty");
+}
+public class ShapeTypeJsonConverter : global::System.Text.Json.Serialization.JsonConverter<ShapeType>
+{
+    public override void Write(
+        global::System.Text.Json.Utf8JsonWriter writer,
+        ShapeType messageType,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        writer.WriteStringValue(messageType.Value);
+    }
+
+    public override ShapeType Read(
+        ref global::System.Text.Json.Utf8JsonReader reader,
+        global::System.Type typeToConvert,
+        global::System.Text.Json.JsonSerializerOptions options)
+    {
+        if (reader.TokenType != global::System.Text.Json.JsonTokenType.String)
+            throw new global::System.Text.Json.JsonException($"Expected string token, but got {reader.TokenType}");
+
+        var value = reader.GetString();
+        return value switch
+        {
+            "Circle" => ShapeType.circle,
+            "Empty" => ShapeType.empty,
+            _ => throw new global::System.Text.Json.JsonException($"Unknown type: {value}")
+        };
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_equality_record_class() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                with_equality: true,
+                structure_kind: CSharpStructureKind::RecordClass,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; tags: string[]; }"),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public record class Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; set; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; set; }
+
+    #nullable enable
+    public virtual bool Equals(Point? other)
+    {
+        return other is not null
+            && global::System.Collections.Generic.EqualityComparer<float>.Default.Equals(this.x, other.x)
+            && (this.tags == other.tags || this.tags is not null && other.tags is not null && global::System.Linq.Enumerable.SequenceEqual(this.tags, other.tags));
+    }
+
+    public override int GetHashCode()
+    {
+        var hash = new global::System.HashCode();
+        hash.Add(this.x);
+        if (this.tags is not null)
+        {
+            foreach (var item in this.tags)
+                hash.Add(item);
+        }
+        return hash.ToHashCode();
+    }
+
+    public override string ToString() => $"Point {{ x = {this.x}, tags = {(this.tags is null ? "null" : "[" + string.Join(", ", this.tags) + "]")} }}";
+    #nullable restore
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_equality_readonly_struct() {
+        let opts = CodeGenOptions {
+            csharp: CSharpCodeGenOptions {
+                with_equality: true,
+                structure_kind: CSharpStructureKind::ReadonlyStruct,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; tags: string[]; }"),
+            r##"using NeatProto;
+
+namespace MyNamespace;
+
+public readonly struct Point
+{
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required float x { get; init; }
+    [global::System.ComponentModel.DataAnnotations.Required]
+    public required global::System.Collections.Generic.List<string> tags { get; init; }
+
+    #nullable enable
+    public bool Equals(Point other)
+    {
+        return global::System.Collections.Generic.EqualityComparer<float>.Default.Equals(this.x, other.x)
+            && (this.tags == other.tags || this.tags is not null && other.tags is not null && global::System.Linq.Enumerable.SequenceEqual(this.tags, other.tags));
+    }
+
+    public override bool Equals(object? obj) => obj is Point other && Equals(other);
+
+    public override int GetHashCode()
+    {
+        var hash = new global::System.HashCode();
+        hash.Add(this.x);
+        if (this.tags is not null)
+        {
+            foreach (var item in this.tags)
+                hash.Add(item);
+        }
+        return hash.ToHashCode();
+    }
+
+    public static bool operator ==(Point left, Point right) => left.Equals(right);
+    public static bool operator !=(Point left, Point right) => !(left == right);
+
+    public override string ToString() => $"Point {{ x = {this.x}, tags = {(this.tags is null ? "null" : "[" + string.Join(", ", this.tags) + "]")} }}";
+    #nullable restore
+}
+"##
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum CodeGenError {
+    /// Option with given name has a value that the generator does not support.
+    InvalidOption(String, String),
+    /// Annotation of a type has arguments that the generator does not support. Contains name
+    /// of the type, name of the annotation and a message.
+    InvalidAnnotation(String, String, String),
+    /// Modules generated from two files cannot be combined into a module tree. Contains paths
+    /// of both files and a message.
+    ModuleConflict(PathBuf, PathBuf, String),
+}
+
+impl Display for CodeGenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodeGenError::InvalidOption(option, message) => {
+                write!(f, "Invalid value of option '{}': {}", option, message)
+            }
+            CodeGenError::InvalidAnnotation(type_name, annotation, message) => {
+                write!(
+                    f,
+                    "Invalid annotation '@{}' of '{}': {}",
+                    annotation, type_name, message
+                )
+            }
+            CodeGenError::ModuleConflict(file_path, other_file_path, message) => {
+                write!(
+                    f,
+                    "Cannot generate modules of '{}' and '{}': {}",
+                    file_path.display(),
+                    other_file_path.display(),
+                    message
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodeGenError {}
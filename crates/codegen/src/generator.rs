@@ -0,0 +1,267 @@
+use crate::rust::RUST_ATTRIBUTE_ANNOTATION;
+use crate::tagged_union::validate_tagged_unions;
+use crate::writer::IndentedWriter;
+use crate::{
+    CONTENT_ANNOTATION, CodeGenError, CodeGenOptions, REPR_ANNOTATION, TAG_ANNOTATION,
+    TaggedUnionRepr, TargetLanguage,
+};
+use clap::ValueEnum;
+use neatproto_ast::{Alias, Block, BlockNode, Enum, Structure};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A backend that turns an analyzed block into source code of some target language.
+///
+/// The default implementation of [`CodeGenerator::generate`] validates the root block, writes
+/// the header, visits every node of the block and then writes the footer, so most backends only
+/// need to implement the `visit_*` methods.
+pub trait CodeGenerator {
+    /// Extension (without the leading dot) of files containing generated code.
+    fn file_extension(&self) -> &str;
+
+    /// Checks that given options are supported by this generator. Called before generating
+    /// code, so that invalid options are reported as errors instead of producing broken code.
+    fn validate_options(&self, _opts: &CodeGenOptions) -> Result<(), CodeGenError> {
+        Ok(())
+    }
+
+    /// Tagged union representations that this generator can produce with given options.
+    fn tagged_union_reprs(&self, _opts: &CodeGenOptions) -> &'static [TaggedUnionRepr] {
+        &TaggedUnionRepr::ALL
+    }
+
+    /// Names of annotations understood by this generator, in addition to the ones known to
+    /// every built-in target.
+    fn custom_annotations(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Checks that given options, as well as annotations of types in the block, are supported
+    /// by this generator. Called before generating code for the block.
+    fn validate(&self, opts: &CodeGenOptions, block: &Block) -> Result<(), CodeGenError> {
+        self.validate_options(opts)?;
+        validate_annotation_names(block, self.custom_annotations())?;
+        validate_tagged_unions(opts, block, self.tagged_union_reprs(opts))
+    }
+
+    fn write_header(&self, _opts: &CodeGenOptions, _writer: &mut IndentedWriter) {}
+
+    fn write_footer(&self, _opts: &CodeGenOptions, _writer: &mut IndentedWriter) {}
+
+    fn visit_block(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        block: &Block,
+    ) -> Result<(), CodeGenError> {
+        for node in &block.nodes {
+            match node {
+                BlockNode::Block(block) => self.visit_nested_block(opts, writer, block)?,
+                BlockNode::Structure(structure) => self.visit_structure(opts, writer, structure)?,
+                BlockNode::Alias(alias) => self.visit_alias(opts, writer, alias)?,
+                BlockNode::Enum(e) => self.visit_enum(opts, writer, e)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Visits a block nested in another block. By default its nodes are written as if they
+    /// were declared directly in the parent block.
+    fn visit_nested_block(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        block: &Block,
+    ) -> Result<(), CodeGenError> {
+        self.visit_block(opts, writer, block)
+    }
+
+    fn visit_structure(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        structure: &Structure,
+    ) -> Result<(), CodeGenError>;
+
+    fn visit_enum(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        e: &Enum,
+    ) -> Result<(), CodeGenError>;
+
+    fn visit_alias(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        alias: &Alias,
+    ) -> Result<(), CodeGenError>;
+
+    fn generate(&self, opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+        self.validate(opts, root_block)?;
+
+        let mut writer = IndentedWriter::default();
+        self.write_header(opts, &mut writer);
+        self.visit_block(opts, &mut writer, root_block)?;
+        self.write_footer(opts, &mut writer);
+        Ok(writer.into_string())
+    }
+
+    /// Generates code split into files, keyed by their paths relative to the output directory.
+    ///
+    /// By default everything is generated into a single file at `file_path`. Generators which
+    /// support splitting the output should place all files in the same directory as `file_path`.
+    fn generate_files(
+        &self,
+        opts: &CodeGenOptions,
+        root_block: &Block,
+        file_path: &Path,
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        Ok(BTreeMap::from([(
+            file_path.to_path_buf(),
+            self.generate(opts, root_block)?,
+        )]))
+    }
+
+    /// Generates code for multiple source files at once, each given as a block along with
+    /// the path its code would be written to. Returns files keyed by their paths relative
+    /// to the output directory.
+    ///
+    /// By default every block is generated on its own using [`CodeGenerator::generate_files`].
+    fn generate_modules(
+        &self,
+        opts: &CodeGenOptions,
+        modules: &[(PathBuf, Block)],
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        let mut files = BTreeMap::new();
+        for (file_path, block) in modules {
+            files.extend(self.generate_files(opts, block, file_path)?);
+        }
+        Ok(files)
+    }
+}
+
+/// Annotations known to every built-in target, so that the same schema can be compiled to any
+/// of them.
+const KNOWN_ANNOTATIONS: &[&str] = &[
+    REPR_ANNOTATION,
+    TAG_ANNOTATION,
+    CONTENT_ANNOTATION,
+    RUST_ATTRIBUTE_ANNOTATION,
+];
+
+/// Rejects annotations which are neither known to every built-in target nor in `custom`, so
+/// that a misspelled annotation is not silently ignored.
+pub fn validate_annotation_names(block: &Block, custom: &[&str]) -> Result<(), CodeGenError> {
+    for node in &block.nodes {
+        let (type_name, annotations) = match node {
+            BlockNode::Block(block) => {
+                validate_annotation_names(block, custom)?;
+                continue;
+            }
+            BlockNode::Structure(structure) => (&structure.name, &structure.annotations),
+            BlockNode::Enum(e) => (&e.name, &e.annotations),
+            BlockNode::Alias(alias) => (&alias.alias_name, &alias.annotations),
+        };
+
+        for annotation in annotations {
+            let name = annotation.name.as_str();
+            if !KNOWN_ANNOTATIONS.contains(&name) && !custom.contains(&name) {
+                return Err(CodeGenError::InvalidAnnotation(
+                    type_name.clone(),
+                    annotation.name.clone(),
+                    format!(
+                        "unknown annotation, expected one of: {}",
+                        [KNOWN_ANNOTATIONS, custom].concat().join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collection of code generators addressable by name.
+///
+/// The default registry contains every built-in [`TargetLanguage`], registered under the same
+/// name that is used on the command line (e.g. `rust`, `csharp`, `json-schema`).
+pub struct CodeGenRegistry {
+    generators: BTreeMap<String, Box<dyn CodeGenerator>>,
+}
+
+impl CodeGenRegistry {
+    /// Creates a registry without any generators, not even the built-in ones.
+    pub fn empty() -> Self {
+        Self {
+            generators: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a generator under given name, replacing any generator previously
+    /// registered under the same name.
+    pub fn register<S: Into<String>, G: CodeGenerator + 'static>(&mut self, name: S, generator: G) {
+        self.generators.insert(name.into(), Box::new(generator));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CodeGenerator> {
+        self.generators
+            .get(name)
+            .map(|generator| generator.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.generators.keys().map(|name| name.as_str())
+    }
+}
+
+impl Default for CodeGenRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for target_language in TargetLanguage::value_variants() {
+            registry
+                .generators
+                .insert(target_language.name(), target_language.generator());
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generator::validate_annotation_names;
+    use crate::tests::parse;
+    use crate::{CodeGenError, CodeGenOptions, TargetLanguage};
+    use neatproto_ast::BlockNode;
+
+    #[test]
+    fn test_validate_annotation_names() {
+        let block = parse(
+            "@rust_attr(\"derive(Hash)\") struct A { x: bool; } @tag(\"type\") enum B { C { x: bool; }, D }",
+        );
+        assert!(validate_annotation_names(&block, &[]).is_ok());
+
+        let mut block = parse("@rust_atr(\"derive(Hash)\") alias B = bool;");
+        assert!(matches!(
+            validate_annotation_names(&block, &[]),
+            Err(CodeGenError::InvalidAnnotation(type_name, annotation, _))
+                if type_name == "B" && annotation == "rust_atr"
+        ));
+        assert!(validate_annotation_names(&block, &["rust_atr"]).is_ok());
+
+        block = parse("alias B = bool;");
+        block
+            .nodes
+            .insert(0, BlockNode::Block(parse("@foo struct A { x: bool; }")));
+        assert!(validate_annotation_names(&block, &[]).is_err());
+
+        for target_language in [TargetLanguage::Kotlin, TargetLanguage::Swift] {
+            assert!(matches!(
+                target_language
+                    .generator()
+                    .generate(&CodeGenOptions::default(), &block),
+                Err(CodeGenError::InvalidAnnotation(..))
+            ));
+        }
+    }
+}
@@ -0,0 +1,662 @@
+use crate::serialization::{enum_discriminants, serialized_name};
+use crate::tagged_union::tag_value;
+use crate::writer::IndentedWriter;
+use crate::{
+    CodeGenError, CodeGenOptions, CodeGenerator, NameCasing, TaggedUnionLayout, TaggedUnionRepr,
+};
+use neatproto_ast::*;
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JsonSchemaCodeGenOptions {
+    pub id: Option<String>,
+}
+
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+pub struct JsonSchemaCodeGenerator;
+
+impl CodeGenerator for JsonSchemaCodeGenerator {
+    fn file_extension(&self) -> &str {
+        "json"
+    }
+
+    fn write_header(&self, opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+        writer.write_line("{");
+        writer.push_indent();
+        writer.write_indented_line(format!("\"$schema\": \"{SCHEMA_DIALECT}\","));
+
+        if let Some(id) = &opts.json_schema.id {
+            writer.write_indented_line(format!("\"$id\": \"{}\",", escape_string(id)));
+        }
+
+        writer.write_indented_line("\"$defs\": {");
+        writer.push_indent();
+    }
+
+    fn write_footer(&self, _opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+        writer.pop_indent();
+        writer.write_indented_line("}");
+        writer.pop_indent();
+        writer.write_line("}");
+    }
+
+    /// Definitions are separated with commas, so nested blocks are flattened into a single
+    /// list of nodes instead of being visited recursively.
+    fn visit_block(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        block: &Block,
+    ) -> Result<(), CodeGenError> {
+        fn flatten<'a>(block: &'a Block, nodes: &mut Vec<&'a BlockNode>) {
+            for node in &block.nodes {
+                match node {
+                    BlockNode::Block(block) => flatten(block, nodes),
+                    _ => nodes.push(node),
+                }
+            }
+        }
+
+        let mut nodes = vec![];
+        flatten(block, &mut nodes);
+
+        for (index, node) in nodes.iter().enumerate() {
+            if index > 0 {
+                writer.write_line(",");
+            }
+
+            match node {
+                BlockNode::Structure(structure) => self.visit_structure(opts, writer, structure)?,
+                BlockNode::Alias(alias) => self.visit_alias(opts, writer, alias)?,
+                BlockNode::Enum(e) => self.visit_enum(opts, writer, e)?,
+                BlockNode::Block(_) => {}
+            }
+        }
+
+        if !nodes.is_empty() {
+            writer.next_line();
+        }
+        Ok(())
+    }
+
+    fn visit_structure(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        structure: &Structure,
+    ) -> Result<(), CodeGenError> {
+        write_definition(
+            writer,
+            &structure.name.to_name_case(opts.type_name_case),
+            structure_schema(opts, structure),
+        );
+        Ok(())
+    }
+
+    fn visit_enum(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        e: &Enum,
+    ) -> Result<(), CodeGenError> {
+        write_definition(
+            writer,
+            &e.name.to_name_case(opts.type_name_case),
+            enum_schema(opts, e)?,
+        );
+        Ok(())
+    }
+
+    fn visit_alias(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        alias: &Alias,
+    ) -> Result<(), CodeGenError> {
+        write_definition(
+            writer,
+            &alias.alias_name.to_name_case(opts.type_name_case),
+            type_schema(opts, &alias.aliased_type_name),
+        );
+        Ok(())
+    }
+}
+
+pub fn generate_json_schema(
+    opts: &CodeGenOptions,
+    root_block: &Block,
+) -> Result<String, CodeGenError> {
+    JsonSchemaCodeGenerator.generate(opts, root_block)
+}
+
+fn write_definition(writer: &mut IndentedWriter, name: &str, schema: JsonValue) {
+    writer.write_indented(format!("\"{}\": ", escape_string(name)));
+    schema.write(writer);
+}
+
+fn structure_schema(opts: &CodeGenOptions, structure: &Structure) -> JsonValue {
+    let (properties, required) = structure_properties(opts, structure);
+    object_schema(properties, required)
+}
+
+/// Returns schemas of all fields of a structure along with a list of their names.
+fn structure_properties(
+    opts: &CodeGenOptions,
+    structure: &Structure,
+) -> (Vec<(String, JsonValue)>, Vec<JsonValue>) {
+    let mut properties = vec![];
+    let mut required = vec![];
+
+    for field in &structure.fields {
+        let field_name = serialized_name(opts, &field.name, opts.field_name_case);
+        properties.push((field_name.clone(), full_type_schema(opts, &field.type_name)));
+        required.push(JsonValue::String(field_name));
+    }
+
+    (properties, required)
+}
+
+fn object_schema(properties: Vec<(String, JsonValue)>, required: Vec<JsonValue>) -> JsonValue {
+    JsonValue::Object(vec![
+        ("type".into(), JsonValue::String("object".into())),
+        ("properties".into(), JsonValue::Object(properties)),
+        ("required".into(), JsonValue::Array(required)),
+        ("additionalProperties".into(), JsonValue::Bool(false)),
+    ])
+}
+
+fn enum_schema(opts: &CodeGenOptions, e: &Enum) -> Result<JsonValue, CodeGenError> {
+    let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
+    if is_tagged_union {
+        return tagged_union_schema(opts, e);
+    }
+
+    // Mirror the Rust target: enums are serialized as their discriminants when
+    // `serde_enum_repr` is set, and as item names otherwise.
+    if opts.rust.serde_enum_repr.is_some() {
+        let values = enum_discriminants(e)
+            .into_iter()
+            .map(|value| JsonValue::Number(value.to_string()))
+            .collect();
+
+        return Ok(JsonValue::Object(vec![
+            ("type".into(), JsonValue::String("integer".into())),
+            ("enum".into(), JsonValue::Array(values)),
+        ]));
+    }
+
+    let names = e
+        .items
+        .iter()
+        .map(|item| JsonValue::String(serialized_name(opts, &item.name, opts.enum_item_name_case)))
+        .collect();
+
+    Ok(JsonValue::Object(vec![
+        ("type".into(), JsonValue::String("string".into())),
+        ("enum".into(), JsonValue::Array(names)),
+    ]))
+}
+
+fn tagged_union_schema(opts: &CodeGenOptions, e: &Enum) -> Result<JsonValue, CodeGenError> {
+    let layout = TaggedUnionLayout::resolve(opts, e)?;
+    let cases = e
+        .items
+        .iter()
+        .map(|item| {
+            let kind = tag_value(opts, item);
+            let kind_schema =
+                JsonValue::Object(vec![("const".into(), JsonValue::String(kind.clone()))]);
+
+            match layout.repr {
+                TaggedUnionRepr::Internal => {
+                    let (mut properties, mut required) = match &item.structure {
+                        Some(structure) => structure_properties(opts, structure),
+                        None => (vec![], vec![]),
+                    };
+                    properties.insert(0, (layout.tag.into(), kind_schema));
+                    required.insert(0, JsonValue::String(layout.tag.into()));
+                    object_schema(properties, required)
+                }
+                TaggedUnionRepr::Adjacent => {
+                    let mut properties = vec![(layout.tag.to_string(), kind_schema)];
+                    let mut required = vec![JsonValue::String(layout.tag.into())];
+
+                    if let Some(structure) = &item.structure {
+                        properties.push((layout.content.into(), structure_schema(opts, structure)));
+                        required.push(JsonValue::String(layout.content.into()));
+                    }
+
+                    object_schema(properties, required)
+                }
+                // Serde writes items without fields as plain strings.
+                TaggedUnionRepr::External => match &item.structure {
+                    Some(structure) => object_schema(
+                        vec![(kind.clone(), structure_schema(opts, structure))],
+                        vec![JsonValue::String(kind)],
+                    ),
+                    None => kind_schema,
+                },
+                TaggedUnionRepr::Untagged => match &item.structure {
+                    Some(structure) => structure_schema(opts, structure),
+                    None => {
+                        JsonValue::Object(vec![("type".into(), JsonValue::String("null".into()))])
+                    }
+                },
+            }
+        })
+        .collect();
+
+    Ok(JsonValue::Object(vec![(
+        "oneOf".into(),
+        JsonValue::Array(cases),
+    )]))
+}
+
+fn full_type_schema(opts: &CodeGenOptions, type_name: &TypeName) -> JsonValue {
+    let items = type_schema(opts, &type_name.token.value());
+    if !type_name.is_array {
+        return items;
+    }
+
+    let mut schema = vec![
+        ("type".into(), JsonValue::String("array".into())),
+        ("items".into(), items),
+    ];
+
+    if let Some(size) = &type_name.array_size {
+        schema.push(("minItems".into(), JsonValue::Number(size.value())));
+        schema.push(("maxItems".into(), JsonValue::Number(size.value())));
+    }
+
+    JsonValue::Object(schema)
+}
+
+fn type_schema(opts: &CodeGenOptions, type_name: &String) -> JsonValue {
+    match BuiltinTypeName::parse(type_name) {
+        Some(builtin) => builtin_type_schema(builtin),
+        None => JsonValue::Object(vec![(
+            "$ref".into(),
+            JsonValue::String(format!(
+                "#/$defs/{}",
+                type_name.to_name_case(opts.type_name_case)
+            )),
+        )]),
+    }
+}
+
+fn builtin_type_schema(type_name: BuiltinTypeName) -> JsonValue {
+    fn integer(minimum: impl ToString, maximum: impl ToString) -> JsonValue {
+        JsonValue::Object(vec![
+            ("type".into(), JsonValue::String("integer".into())),
+            ("minimum".into(), JsonValue::Number(minimum.to_string())),
+            ("maximum".into(), JsonValue::Number(maximum.to_string())),
+        ])
+    }
+
+    fn simple(type_name: &str) -> JsonValue {
+        JsonValue::Object(vec![("type".into(), JsonValue::String(type_name.into()))])
+    }
+
+    match type_name {
+        BuiltinTypeName::Uint8 => integer(u8::MIN, u8::MAX),
+        BuiltinTypeName::Uint16 => integer(u16::MIN, u16::MAX),
+        BuiltinTypeName::Uint32 => integer(u32::MIN, u32::MAX),
+        BuiltinTypeName::Uint64 => integer(u64::MIN, u64::MAX),
+        BuiltinTypeName::Uint128 => integer(u128::MIN, u128::MAX),
+        BuiltinTypeName::Int8 => integer(i8::MIN, i8::MAX),
+        BuiltinTypeName::Int16 => integer(i16::MIN, i16::MAX),
+        BuiltinTypeName::Int32 => integer(i32::MIN, i32::MAX),
+        BuiltinTypeName::Int64 => integer(i64::MIN, i64::MAX),
+        BuiltinTypeName::Int128 => integer(i128::MIN, i128::MAX),
+        BuiltinTypeName::Float32 | BuiltinTypeName::Float64 => simple("number"),
+        BuiltinTypeName::Bool => simple("boolean"),
+        BuiltinTypeName::String => simple("string"),
+    }
+}
+
+enum JsonValue {
+    Object(Vec<(String, JsonValue)>),
+    Array(Vec<JsonValue>),
+    String(String),
+    Number(String),
+    Bool(bool),
+}
+
+impl JsonValue {
+    fn write(&self, writer: &mut IndentedWriter) {
+        match self {
+            JsonValue::Object(entries) => {
+                if entries.is_empty() {
+                    writer.write("{}");
+                    return;
+                }
+
+                writer.write_line("{");
+                writer.push_indent();
+
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    writer.write_indented(format!("\"{}\": ", escape_string(key)));
+                    value.write(writer);
+                    if index + 1 < entries.len() {
+                        writer.write(",");
+                    }
+                    writer.next_line();
+                }
+
+                writer.pop_indent();
+                writer.write_indented("}");
+            }
+            JsonValue::Array(values) => {
+                if values.is_empty() {
+                    writer.write("[]");
+                    return;
+                }
+
+                writer.write_line("[");
+                writer.push_indent();
+
+                for (index, value) in values.iter().enumerate() {
+                    writer.write_indent();
+                    value.write(writer);
+                    if index + 1 < values.len() {
+                        writer.write(",");
+                    }
+                    writer.next_line();
+                }
+
+                writer.pop_indent();
+                writer.write_indented("]");
+            }
+            JsonValue::String(value) => {
+                writer.write_string(format!("\"{}\"", escape_string(value)));
+            }
+            JsonValue::Number(value) => writer.write(value),
+            JsonValue::Bool(value) => writer.write_string(value),
+        }
+    }
+}
+
+fn escape_string(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_schema::generate_json_schema;
+    use crate::tests::parse;
+    use crate::{Case, CodeGenOptions, JsonSchemaCodeGenOptions, NameCase, RustCodeGenOptions};
+    use neatproto_ast::{Block, BlockNode};
+
+    fn generate(opts: &CodeGenOptions, source: &str) -> String {
+        generate_json_schema(opts, &parse(source)).expect("failed to generate code")
+    }
+
+    #[test]
+    fn test_structure() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                "alias Id = uint8; struct Line { id: Id; name: string; points: float[]; ends: float[2]; }",
+            ),
+            r##"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$defs": {
+        "Id": {
+            "type": "integer",
+            "minimum": 0,
+            "maximum": 255
+        },
+        "Line": {
+            "type": "object",
+            "properties": {
+                "id": {
+                    "$ref": "#/$defs/Id"
+                },
+                "name": {
+                    "type": "string"
+                },
+                "points": {
+                    "type": "array",
+                    "items": {
+                        "type": "number"
+                    }
+                },
+                "ends": {
+                    "type": "array",
+                    "items": {
+                        "type": "number"
+                    },
+                    "minItems": 2,
+                    "maxItems": 2
+                }
+            },
+            "required": [
+                "id",
+                "name",
+                "points",
+                "ends"
+            ],
+            "additionalProperties": false
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_id() {
+        let opts = CodeGenOptions {
+            json_schema: JsonSchemaCodeGenOptions {
+                id: Some("https://example.com/schema.json".into()),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Empty { }"),
+            r##"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$id": "https://example.com/schema.json",
+    "$defs": {
+        "Empty": {
+            "type": "object",
+            "properties": {},
+            "required": [],
+            "additionalProperties": false
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_enum_by_name() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                serde_struct_field_name_case: NameCase::Other(Case::Camel),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Color { light_red = 1, dark_blue }"),
+            r##"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$defs": {
+        "Color": {
+            "type": "string",
+            "enum": [
+                "lightRed",
+                "darkBlue"
+            ]
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_enum_by_value() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                serde_enum_repr: Some("u8".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Color { Red = 1, Green, Blue = 5 }"),
+            r##"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$defs": {
+        "Color": {
+            "type": "integer",
+            "enum": [
+                1,
+                2,
+                5
+            ]
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(&opts, "enum Shape { Circle { radius: float; }, Empty }"),
+            r##"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$defs": {
+        "Shape": {
+            "oneOf": [
+                {
+                    "type": "object",
+                    "properties": {
+                        "kind": {
+                            "const": "Circle"
+                        },
+                        "value": {
+                            "type": "object",
+                            "properties": {
+                                "radius": {
+                                    "type": "number"
+                                }
+                            },
+                            "required": [
+                                "radius"
+                            ],
+                            "additionalProperties": false
+                        }
+                    },
+                    "required": [
+                        "kind",
+                        "value"
+                    ],
+                    "additionalProperties": false
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "kind": {
+                            "const": "Empty"
+                        }
+                    },
+                    "required": [
+                        "kind"
+                    ],
+                    "additionalProperties": false
+                }
+            ]
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_internally_tagged_union() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                r#"@repr(internal) @tag("type") enum Shape { Circle { radius: float; }, Empty }"#,
+            ),
+            r##"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$defs": {
+        "Shape": {
+            "oneOf": [
+                {
+                    "type": "object",
+                    "properties": {
+                        "type": {
+                            "const": "Circle"
+                        },
+                        "radius": {
+                            "type": "number"
+                        }
+                    },
+                    "required": [
+                        "type",
+                        "radius"
+                    ],
+                    "additionalProperties": false
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "type": {
+                            "const": "Empty"
+                        }
+                    },
+                    "required": [
+                        "type"
+                    ],
+                    "additionalProperties": false
+                }
+            ]
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let opts = CodeGenOptions::default();
+        let block = Block {
+            nodes: vec![
+                BlockNode::Block(parse("struct A { x: bool; }")),
+                BlockNode::Block(parse("struct B { a: A; }")),
+            ],
+        };
+
+        // Definitions of nested blocks are merged into a single `$defs` object.
+        assert_eq!(
+            generate_json_schema(&opts, &block).expect("failed to generate code"),
+            generate(&opts, "struct A { x: bool; } struct B { a: A; }")
+        );
+    }
+}
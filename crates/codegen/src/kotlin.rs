@@ -0,0 +1,594 @@
+use crate::serialization::{enum_discriminants, serialized_name};
+use crate::tagged_union::tag_value;
+use crate::writer::IndentedWriter;
+use crate::{
+    CodeGenError, CodeGenOptions, CodeGenerator, DEFAULT_CONTENT_FIELD_NAME, NameCasing,
+    TaggedUnionLayout, TaggedUnionRepr,
+};
+use neatproto_ast::*;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KotlinCodeGenOptions {
+    pub package: String,
+    pub with_serialization: bool,
+}
+
+impl Default for KotlinCodeGenOptions {
+    fn default() -> Self {
+        Self {
+            package: "proto".into(),
+            with_serialization: true,
+        }
+    }
+}
+
+pub struct KotlinCodeGenerator;
+
+impl CodeGenerator for KotlinCodeGenerator {
+    fn file_extension(&self) -> &str {
+        "kt"
+    }
+
+    fn tagged_union_reprs(&self, _opts: &CodeGenOptions) -> &'static [TaggedUnionRepr] {
+        &[TaggedUnionRepr::Internal, TaggedUnionRepr::Adjacent]
+    }
+
+    fn write_header(&self, opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+        writer.write_string_line(format!("package {}", opts.kotlin.package));
+        writer.next_line();
+
+        if opts.kotlin.with_serialization {
+            writer.write_line("import kotlinx.serialization.Contextual");
+            writer.write_line("import kotlinx.serialization.ExperimentalSerializationApi");
+            writer.write_line("import kotlinx.serialization.SerialName");
+            writer.write_line("import kotlinx.serialization.Serializable");
+            writer.write_line("import kotlinx.serialization.json.JsonClassDiscriminator");
+
+            // Enums serialized as their values use custom serializers.
+            if opts.rust.serde_enum_repr.is_some() {
+                writer.write_line("import kotlinx.serialization.KSerializer");
+                writer.write_line("import kotlinx.serialization.SerializationException");
+                writer.write_line("import kotlinx.serialization.descriptors.PrimitiveKind");
+                writer.write_line(
+                    "import kotlinx.serialization.descriptors.PrimitiveSerialDescriptor",
+                );
+                writer.write_line("import kotlinx.serialization.encoding.Decoder");
+                writer.write_line("import kotlinx.serialization.encoding.Encoder");
+            }
+
+            writer.next_line();
+        }
+    }
+
+    fn visit_structure(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        structure: &Structure,
+    ) -> Result<(), CodeGenError> {
+        write_structure(opts, writer, structure);
+        Ok(())
+    }
+
+    fn visit_enum(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        e: &Enum,
+    ) -> Result<(), CodeGenError> {
+        write_enum(opts, writer, e)
+    }
+
+    fn visit_alias(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        alias: &Alias,
+    ) -> Result<(), CodeGenError> {
+        write_alias(opts, writer, alias);
+        Ok(())
+    }
+}
+
+pub fn generate_kotlin(opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+    KotlinCodeGenerator.generate(opts, root_block)
+}
+
+fn write_serializable_attr(opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+    if opts.kotlin.with_serialization {
+        writer.write_indented_line("@Serializable");
+    }
+}
+
+/// Returns `@SerialName` annotation renaming a declaration to the name used by the Rust
+/// target, or an empty string if the names are the same.
+fn serial_name_attr(opts: &CodeGenOptions, declared_name: &str, serialized_name: &str) -> String {
+    if opts.kotlin.with_serialization && declared_name != serialized_name {
+        format!("@SerialName(\"{serialized_name}\") ")
+    } else {
+        String::new()
+    }
+}
+
+fn write_structure(opts: &CodeGenOptions, writer: &mut IndentedWriter, structure: &Structure) {
+    write_serializable_attr(opts, writer);
+    write_data_class(
+        opts,
+        writer,
+        &structure.name.to_name_case(opts.type_name_case),
+        structure,
+    );
+    writer.next_line();
+    writer.next_line();
+}
+
+/// Writes a `data class` with a primary constructor containing every field of the structure.
+/// Kotlin does not allow data classes without any properties, so empty structures are
+/// written as regular classes instead.
+fn write_data_class(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    class_name: &str,
+    structure: &Structure,
+) {
+    if structure.fields.is_empty() {
+        writer.write_indented(format!("class {class_name}"));
+        return;
+    }
+
+    writer.write_indented_line(format!("data class {class_name}("));
+    writer.push_indent();
+
+    for field in &structure.fields {
+        let field_name = field.name.to_name_case(opts.field_name_case);
+        writer.write_indented_line(format!(
+            "{}val {}: {},",
+            serial_name_attr(
+                opts,
+                &field_name,
+                &serialized_name(opts, &field.name, opts.field_name_case)
+            ),
+            field_name,
+            get_full_type_name(opts, &field.type_name)
+        ));
+    }
+
+    writer.pop_indent();
+    writer.write_indented(")");
+}
+
+fn write_alias(opts: &CodeGenOptions, writer: &mut IndentedWriter, alias: &Alias) {
+    writer.write_indented_line(format!(
+        "typealias {} = {}",
+        alias.alias_name.to_name_case(opts.type_name_case),
+        translate_type_name(opts, &alias.aliased_type_name)
+    ));
+    writer.next_line();
+}
+
+fn write_enum(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
+    let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
+    if is_tagged_union {
+        return write_tagged_union(opts, writer, e);
+    }
+
+    if opts.kotlin.with_serialization && opts.rust.serde_enum_repr.is_some() {
+        write_enum_with_values(opts, writer, e);
+        return Ok(());
+    }
+
+    write_serializable_attr(opts, writer);
+    writer.write_indented_line(format!(
+        "enum class {} {{",
+        e.name.to_name_case(opts.type_name_case)
+    ));
+    writer.push_indent();
+
+    for item in &e.items {
+        let item_name = item.name.to_name_case(opts.enum_item_name_case);
+        writer.write_indented_line(format!(
+            "{}{},",
+            serial_name_attr(
+                opts,
+                &item_name,
+                &serialized_name(opts, &item.name, opts.enum_item_name_case)
+            ),
+            item_name
+        ));
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.next_line();
+    Ok(())
+}
+
+/// Writes an enum serialized as the discriminants of its items, which is how the Rust target
+/// serializes enums when `serde_enum_repr` is set.
+fn write_enum_with_values(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
+    let enum_class_name = e.name.to_name_case(opts.type_name_case);
+    let serializer_name = format!("{enum_class_name}Serializer");
+
+    writer.write_indented_line(format!("@Serializable(with = {serializer_name}::class)"));
+    writer.write_indented_line(format!("enum class {enum_class_name}(val value: Long) {{"));
+    writer.push_indent();
+
+    for (item, value) in e.items.iter().zip(enum_discriminants(e)) {
+        writer.write_indented_line(format!(
+            "{}({value}),",
+            item.name.to_name_case(opts.enum_item_name_case)
+        ));
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.next_line();
+
+    writer.write_indented_lines(format!(
+        r#"object {serializer_name} : KSerializer<{enum_class_name}> {{
+    override val descriptor = PrimitiveSerialDescriptor("{enum_class_name}", PrimitiveKind.LONG)
+
+    override fun serialize(encoder: Encoder, value: {enum_class_name}) = encoder.encodeLong(value.value)
+
+    override fun deserialize(decoder: Decoder): {enum_class_name} {{
+        val value = decoder.decodeLong()
+        return {enum_class_name}.entries.firstOrNull {{ it.value == value }}
+            ?: throw SerializationException("Unknown value of {enum_class_name}: $value")
+    }}
+}}"#
+    ));
+    writer.next_line();
+}
+
+/// Tagged unions are written as sealed class hierarchies using the tag field as the class
+/// discriminator, which matches the internally tagged layout. The adjacently tagged layout
+/// (`{ "kind": "...", "value": { ... } }`) is reproduced by wrapping the fields of each case
+/// in a nested `Value` class.
+fn write_tagged_union(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
+    let enum_class_name = e.name.to_name_case(opts.type_name_case);
+    let layout = TaggedUnionLayout::resolve(opts, e)?;
+
+    if opts.kotlin.with_serialization {
+        writer.write_indented_line("@OptIn(ExperimentalSerializationApi::class)");
+        writer.write_indented_line("@Serializable");
+        writer.write_indented_line(format!("@JsonClassDiscriminator(\"{}\")", layout.tag));
+    }
+
+    writer.write_indented_line(format!("sealed class {enum_class_name} {{"));
+    writer.push_indent();
+
+    for (index, item) in e.items.iter().enumerate() {
+        if index > 0 {
+            writer.next_line();
+        }
+
+        let item_name = item.name.to_name_case(opts.enum_item_name_case);
+
+        write_serializable_attr(opts, writer);
+        if opts.kotlin.with_serialization {
+            writer.write_indented_line(format!("@SerialName(\"{}\")", tag_value(opts, item)));
+        }
+
+        match &item.structure {
+            Some(structure) if layout.repr == TaggedUnionRepr::Internal => {
+                write_data_class(opts, writer, &item_name, structure);
+                writer.write_string_line(format!(" : {enum_class_name}()"));
+            }
+            Some(structure) => {
+                let content_attr = match opts.kotlin.with_serialization {
+                    true if layout.content != DEFAULT_CONTENT_FIELD_NAME => {
+                        format!("@SerialName(\"{}\") ", layout.content)
+                    }
+                    _ => String::new(),
+                };
+                writer.write_indented_line(format!(
+                    "data class {item_name}({content_attr}val value: Value) : {enum_class_name}() {{"
+                ));
+                writer.push_indent();
+                write_serializable_attr(opts, writer);
+                write_data_class(opts, writer, "Value", structure);
+                writer.next_line();
+                writer.pop_indent();
+                writer.write_indented_line("}");
+            }
+            None => {
+                writer
+                    .write_indented_line(format!("data object {item_name} : {enum_class_name}()"));
+            }
+        }
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.next_line();
+    Ok(())
+}
+
+fn get_full_type_name(opts: &CodeGenOptions, type_name: &TypeName) -> String {
+    let name = translate_type_name(opts, &type_name.token.value());
+    if type_name.is_array {
+        return format!("List<{}>", name);
+    }
+    name
+}
+
+fn translate_type_name(opts: &CodeGenOptions, type_name: &String) -> String {
+    match BuiltinTypeName::parse(type_name) {
+        // kotlinx.serialization has no built-in serializer for `BigInteger`, so it has to be
+        // provided by the user through a serializers module.
+        Some(t @ (BuiltinTypeName::Uint128 | BuiltinTypeName::Int128))
+            if opts.kotlin.with_serialization =>
+        {
+            format!("@Contextual {}", translate_builtin_type_name(t))
+        }
+        Some(t) => translate_builtin_type_name(t).to_string(),
+        None => type_name.to_name_case(opts.type_name_case),
+    }
+}
+
+fn translate_builtin_type_name(type_name: BuiltinTypeName) -> &'static str {
+    match type_name {
+        BuiltinTypeName::Uint8 => "UByte",
+        BuiltinTypeName::Uint16 => "UShort",
+        BuiltinTypeName::Uint32 => "UInt",
+        BuiltinTypeName::Uint64 => "ULong",
+        BuiltinTypeName::Uint128 => "java.math.BigInteger",
+        BuiltinTypeName::Int8 => "Byte",
+        BuiltinTypeName::Int16 => "Short",
+        BuiltinTypeName::Int32 => "Int",
+        BuiltinTypeName::Int64 => "Long",
+        BuiltinTypeName::Int128 => "java.math.BigInteger",
+        BuiltinTypeName::Float32 => "Float",
+        BuiltinTypeName::Float64 => "Double",
+        BuiltinTypeName::Bool => "Boolean",
+        BuiltinTypeName::String => "String",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kotlin::generate_kotlin;
+    use crate::tests::parse;
+    use crate::{Case, CodeGenOptions, KotlinCodeGenOptions, NameCase, RustCodeGenOptions};
+
+    fn generate(opts: &CodeGenOptions, source: &str) -> String {
+        generate_kotlin(opts, &parse(source)).expect("failed to generate code")
+    }
+
+    #[test]
+    fn test_structure() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                "alias Id = uint64; struct Point { x: float; y: float; } struct Line { points: Point[]; ends: Point[2]; id: Id; big: int128; } enum Color { Red, Green }",
+            ),
+            r##"package proto
+
+import kotlinx.serialization.Contextual
+import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonClassDiscriminator
+
+typealias Id = ULong
+
+@Serializable
+data class Point(
+    val x: Float,
+    val y: Float,
+)
+
+@Serializable
+data class Line(
+    val points: List<Point>,
+    val ends: List<Point>,
+    val id: Id,
+    val big: @Contextual java.math.BigInteger,
+)
+
+@Serializable
+enum class Color {
+    Red,
+    Green,
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_without_serialization() {
+        let opts = CodeGenOptions {
+            kotlin: KotlinCodeGenOptions {
+                package: "app.proto".into(),
+                with_serialization: false,
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                "struct Point { x: float; big: int128; } enum Color { Red, Green }",
+            ),
+            r##"package app.proto
+
+data class Point(
+    val x: Float,
+    val big: java.math.BigInteger,
+)
+
+enum class Color {
+    Red,
+    Green,
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                serde_struct_field_name_case: NameCase::Other(Case::Camel),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                "struct Point { pos_x: float; } enum Color { light_red, dark_blue } enum Shape { big_circle { center_point: Point; }, empty }",
+            ),
+            r##"package proto
+
+import kotlinx.serialization.Contextual
+import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonClassDiscriminator
+
+@Serializable
+data class Point(
+    @SerialName("posX") val pos_x: Float,
+)
+
+@Serializable
+enum class Color {
+    @SerialName("lightRed") light_red,
+    @SerialName("darkBlue") dark_blue,
+}
+
+@OptIn(ExperimentalSerializationApi::class)
+@Serializable
+@JsonClassDiscriminator("kind")
+sealed class Shape {
+    @Serializable
+    @SerialName("bigCircle")
+    data class big_circle(val value: Value) : Shape() {
+        @Serializable
+        data class Value(
+            @SerialName("centerPoint") val center_point: Point,
+        )
+    }
+
+    @Serializable
+    @SerialName("empty")
+    data object empty : Shape()
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_enum_with_values() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                serde_enum_repr: Some("u8".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Color { Red = 1, Green, Blue = 5 }"),
+            r##"package proto
+
+import kotlinx.serialization.Contextual
+import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonClassDiscriminator
+import kotlinx.serialization.KSerializer
+import kotlinx.serialization.SerializationException
+import kotlinx.serialization.descriptors.PrimitiveKind
+import kotlinx.serialization.descriptors.PrimitiveSerialDescriptor
+import kotlinx.serialization.encoding.Decoder
+import kotlinx.serialization.encoding.Encoder
+
+@Serializable(with = ColorSerializer::class)
+enum class Color(val value: Long) {
+    Red(1),
+    Green(2),
+    Blue(5),
+}
+
+object ColorSerializer : KSerializer<Color> {
+    override val descriptor = PrimitiveSerialDescriptor("Color", PrimitiveKind.LONG)
+
+    override fun serialize(encoder: Encoder, value: Color) = encoder.encodeLong(value.value)
+
+    override fun deserialize(decoder: Decoder): Color {
+        val value = decoder.decodeLong()
+        return Color.entries.firstOrNull { it.value == value }
+            ?: throw SerializationException("Unknown value of Color: $value")
+    }
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_layouts() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                r#"@repr(internal) @tag("type") enum Shape { Circle { radius: float; }, Empty } @tag("type") @content("data") enum Message { Text { body: string; }, Ping }"#,
+            ),
+            r##"package proto
+
+import kotlinx.serialization.Contextual
+import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonClassDiscriminator
+
+@OptIn(ExperimentalSerializationApi::class)
+@Serializable
+@JsonClassDiscriminator("type")
+sealed class Shape {
+    @Serializable
+    @SerialName("Circle")
+    data class Circle(
+        val radius: Float,
+    ) : Shape()
+
+    @Serializable
+    @SerialName("Empty")
+    data object Empty : Shape()
+}
+
+@OptIn(ExperimentalSerializationApi::class)
+@Serializable
+@JsonClassDiscriminator("type")
+sealed class Message {
+    @Serializable
+    @SerialName("Text")
+    data class Text(@SerialName("data") val value: Value) : Message() {
+        @Serializable
+        data class Value(
+            val body: String,
+        )
+    }
+
+    @Serializable
+    @SerialName("Ping")
+    data object Ping : Message()
+}
+
+"##
+        );
+    }
+}
@@ -1,13 +1,28 @@
 pub mod csharp;
+mod error;
+mod generator;
+pub mod json_schema;
+pub mod kotlin;
 pub mod rust;
+mod serialization;
+pub mod swift;
+mod tagged_union;
 mod writer;
 
-pub use crate::csharp::CSharpCodeGenOptions;
-use crate::csharp::generate_csharp;
-pub use crate::rust::RustCodeGenOptions;
-use crate::rust::generate_rust;
+pub use crate::csharp::{
+    CSharpCodeGenOptions, CSharpCodeGenerator, CSharpJsonLibrary, CSharpStructureKind,
+};
+pub use crate::error::*;
+pub use crate::generator::*;
+pub use crate::json_schema::{JsonSchemaCodeGenOptions, JsonSchemaCodeGenerator};
+pub use crate::kotlin::{KotlinCodeGenOptions, KotlinCodeGenerator};
+pub use crate::rust::{RustCodeGenOptions, RustCodeGenerator};
+pub use crate::swift::{SwiftCodeGenOptions, SwiftCodeGenerator};
+pub use crate::tagged_union::*;
+pub use crate::writer::IndentedWriter;
 pub use convert_case::{Case, Casing};
 use neatproto_ast::Block;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Default, Copy, Clone)]
 pub enum NameCase {
@@ -16,6 +31,39 @@ pub enum NameCase {
     Other(Case<'static>),
 }
 
+impl std::str::FromStr for NameCase {
+    type Err = String;
+
+    /// Parses a name case from strings like `camel`, `snake` or `pascal`.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let case = match string.to_lowercase().replace('_', "-").as_str() {
+            "unchanged" => return Ok(NameCase::Unchanged),
+            "lower" => Case::Lower,
+            "upper" => Case::Upper,
+            "camel" => Case::Camel,
+            "pascal" | "upper-camel" => Case::Pascal,
+            "snake" => Case::Snake,
+            "upper-snake" | "screaming-snake" | "constant" => Case::UpperSnake,
+            "kebab" => Case::Kebab,
+            "upper-kebab" | "screaming-kebab" | "cobol" => Case::UpperKebab,
+            "train" => Case::Train,
+            "flat" => Case::Flat,
+            "upper-flat" => Case::UpperFlat,
+            "title" => Case::Title,
+            "sentence" => Case::Sentence,
+            _ => return Err(format!("unknown name case '{}'", string)),
+        };
+        Ok(NameCase::Other(case))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for NameCase {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 pub trait NameCasing<T: AsRef<str>> {
     fn to_name_case(&self, case: NameCase) -> String;
 }
@@ -34,22 +82,154 @@ pub enum TargetLanguage {
     #[default]
     Rust,
     Csharp,
+    Kotlin,
+    Swift,
+    JsonSchema,
+}
+
+impl TargetLanguage {
+    /// Name of the target, as accepted on the command line.
+    pub fn name(&self) -> String {
+        clap::ValueEnum::to_possible_value(self)
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as clap::ValueEnum>::from_str(name, true).ok()
+    }
+
+    pub fn generator(&self) -> Box<dyn CodeGenerator> {
+        match self {
+            TargetLanguage::Rust => Box::new(RustCodeGenerator),
+            TargetLanguage::Csharp => Box::new(CSharpCodeGenerator),
+            TargetLanguage::Kotlin => Box::new(KotlinCodeGenerator),
+            TargetLanguage::Swift => Box::new(SwiftCodeGenerator),
+            TargetLanguage::JsonSchema => Box::new(JsonSchemaCodeGenerator),
+        }
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CodeGenOptions {
+    /// Built-in target used by [`generate_code`]. Generators looked up in a [`CodeGenRegistry`]
+    /// are used regardless of it.
+    #[serde(skip)]
     pub target_language: TargetLanguage,
     pub field_name_case: NameCase,
     pub type_name_case: NameCase,
     pub enum_item_name_case: NameCase,
+    pub tagged_union_repr: TaggedUnionRepr,
 
     pub rust: RustCodeGenOptions,
     pub csharp: CSharpCodeGenOptions,
+    pub kotlin: KotlinCodeGenOptions,
+    pub swift: SwiftCodeGenOptions,
+    pub json_schema: JsonSchemaCodeGenOptions,
+
+    /// Options of generators registered in a [`CodeGenRegistry`] by programs embedding
+    /// NeatProto, keyed by option name. Built-in generators ignore them.
+    pub custom: BTreeMap<String, String>,
 }
 
-pub fn generate_code(opts: &CodeGenOptions, root_block: &Block) -> String {
-    match opts.target_language {
-        TargetLanguage::Rust => generate_rust(opts, root_block),
-        TargetLanguage::Csharp => generate_csharp(opts, root_block),
+pub fn generate_code(opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+    opts.target_language.generator().generate(opts, root_block)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        CSharpCodeGenOptions, Case, CodeGenOptions, NameCase, RustCodeGenOptions, TaggedUnionRepr,
+        TargetLanguage,
+    };
+    use neatproto_ast::Block;
+    use neatproto_parser::{SourceFile, parse_block};
+
+    pub(crate) fn parse(source: &str) -> Block {
+        let source_file = SourceFile::new_from_source("test", source);
+        parse_block(&mut source_file.tokens()).expect("failed to parse")
+    }
+
+    #[test]
+    fn test_parse_name_case() {
+        assert!(matches!("unchanged".parse(), Ok(NameCase::Unchanged)));
+        assert!(matches!("camel".parse(), Ok(NameCase::Other(Case::Camel))));
+        assert!(matches!(
+            "Pascal".parse(),
+            Ok(NameCase::Other(Case::Pascal))
+        ));
+        assert!(matches!(
+            "screaming_snake".parse(),
+            Ok(NameCase::Other(Case::UpperSnake))
+        ));
+        assert!("foo".parse::<NameCase>().is_err());
+    }
+
+    /// Tags are a part of the JSON layout of tagged unions, so every target must use the same
+    /// values as serde does in the Rust target.
+    #[test]
+    fn test_tagged_union_tags() {
+        let block = parse("enum Shape { BigCircle { r: float; }, Empty }");
+        let mut opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                serde_struct_field_name_case: NameCase::Other(Case::Camel),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let generate = |opts: &CodeGenOptions, target_language: TargetLanguage| {
+            target_language
+                .generator()
+                .generate(opts, &block)
+                .expect("failed to generate code")
+        };
+        let expected_tags: [(TargetLanguage, &[&str]); 5] = [
+            (
+                TargetLanguage::Rust,
+                &["#[serde(rename_all = \"camelCase\")]"],
+            ),
+            (
+                TargetLanguage::Csharp,
+                &[
+                    "TaggedUnionCase(\"bigCircle\", typeof(Shape.BigCircle), true)",
+                    "TaggedUnionCase(\"empty\", typeof(Shape.Empty), false)",
+                    "public static readonly ShapeType bigCircle = new(\"bigCircle\");",
+                    "\"bigCircle\" => ShapeType.bigCircle,",
+                ],
+            ),
+            (
+                TargetLanguage::Kotlin,
+                &["@SerialName(\"bigCircle\")", "@SerialName(\"empty\")"],
+            ),
+            (
+                TargetLanguage::Swift,
+                &[
+                    "case \"bigCircle\":",
+                    "try container.encode(\"empty\", forKey: .kind)",
+                ],
+            ),
+            (
+                TargetLanguage::JsonSchema,
+                &["\"const\": \"bigCircle\"", "\"const\": \"empty\""],
+            ),
+        ];
+        for (target_language, tags) in expected_tags {
+            let code = generate(&opts, target_language);
+            for tag in tags {
+                assert!(code.contains(tag), "{tag} not found in:\n{code}");
+            }
+        }
+
+        opts.tagged_union_repr = TaggedUnionRepr::Internal;
+        opts.csharp = CSharpCodeGenOptions {
+            with_json_polymorphic: true,
+            ..Default::default()
+        };
+        let code = generate(&opts, TargetLanguage::Csharp);
+        assert!(code.contains("JsonDerivedType(typeof(Shape.BigCircle), \"bigCircle\")"));
+        assert!(code.contains("JsonDerivedType(typeof(Shape.Empty), \"empty\")"));
     }
 }
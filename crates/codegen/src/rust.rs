@@ -1,51 +1,406 @@
 use crate::writer::IndentedWriter;
-use crate::{CodeGenOptions, NameCase, NameCasing};
-use convert_case::Case;
+use crate::{
+    CodeGenError, CodeGenOptions, CodeGenerator, NameCase, NameCasing, TaggedUnionLayout,
+    TaggedUnionRepr,
+};
+use convert_case::{Case, Casing};
 use neatproto_ast::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Default)]
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct RustCodeGenOptions {
     pub with_debug: bool,
     pub with_serde: bool,
     pub serde_struct_field_name_case: NameCase,
     pub serde_enum_repr: Option<String>,
     pub with_enum_display: bool,
+    /// When compiling multiple files, generate a module per file along with `mod.rs` files
+    /// declaring them, instead of generating every file on its own.
+    pub module_tree: bool,
+    /// Refer to serde traits using fully qualified paths instead of importing them, which is
+    /// necessary when generated code shares a module with other code.
+    pub serde_qualified_paths: bool,
+    /// Start generated files with `#![allow(...)]` for lints triggered by generated code.
+    /// Inner attributes are not allowed in files included using `include!`.
+    pub with_allow_lints: bool,
+    /// Additional traits to derive, e.g. `PartialEq` or `Hash`. Derives which are not valid
+    /// for a type (like `Eq` for types containing floats) are skipped for that type.
+    pub extra_derives: Vec<String>,
 }
 
-pub fn generate_rust(opts: &CodeGenOptions, root_block: &Block) -> String {
-    let mut writer = IndentedWriter::default();
-    write_block(opts, &mut writer, root_block, false);
-    writer.to_string()
+impl Default for RustCodeGenOptions {
+    fn default() -> Self {
+        Self {
+            with_debug: false,
+            with_serde: false,
+            serde_struct_field_name_case: NameCase::default(),
+            serde_enum_repr: None,
+            with_enum_display: false,
+            module_tree: false,
+            serde_qualified_paths: false,
+            with_allow_lints: true,
+            extra_derives: vec![],
+        }
+    }
+}
+
+pub struct RustCodeGenerator;
+
+impl CodeGenerator for RustCodeGenerator {
+    fn file_extension(&self) -> &str {
+        "rs"
+    }
+
+    fn validate_options(&self, opts: &CodeGenOptions) -> Result<(), CodeGenError> {
+        if let NameCase::Other(case) = opts.rust.serde_struct_field_name_case {
+            serde_rename_case(&case)?;
+        }
+
+        if let Some(repr) = &opts.rust.serde_enum_repr
+            && !SERDE_ENUM_REPRS.contains(&repr.as_str())
+        {
+            return Err(CodeGenError::InvalidOption(
+                "serde_enum_repr".to_string(),
+                format!("'{}' is not a primitive integer type", repr),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn visit_nested_block(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        block: &Block,
+    ) -> Result<(), CodeGenError> {
+        writer.write_indented_line("{");
+        writer.push_indent();
+        self.visit_block(opts, writer, block)?;
+        writer.pop_indent();
+        writer.write_indented_line("}");
+        Ok(())
+    }
+
+    fn visit_structure(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        structure: &Structure,
+    ) -> Result<(), CodeGenError> {
+        write_structure(opts, writer, structure)
+    }
+
+    fn visit_enum(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        e: &Enum,
+    ) -> Result<(), CodeGenError> {
+        write_enum(opts, writer, e)
+    }
+
+    fn visit_alias(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        alias: &Alias,
+    ) -> Result<(), CodeGenError> {
+        write_alias(opts, writer, alias);
+        Ok(())
+    }
+
+    fn generate(&self, opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+        self.validate(opts, root_block)?;
+
+        let mut writer = IndentedWriter::default();
+        write_header(opts, &mut writer, root_block);
+        self.visit_block(opts, &mut writer, root_block)?;
+        Ok(writer.into_string())
+    }
+
+    fn generate_modules(
+        &self,
+        opts: &CodeGenOptions,
+        modules: &[(PathBuf, Block)],
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        let mut files = BTreeMap::new();
+
+        if !opts.rust.module_tree {
+            for (file_path, block) in modules {
+                files.extend(self.generate_files(opts, block, file_path)?);
+            }
+            return Ok(files);
+        }
+
+        for (_, block) in modules {
+            self.validate(opts, block)?;
+        }
+
+        let modules = modules
+            .iter()
+            .map(|(file_path, block)| (file_path, module_path(file_path), block))
+            .collect::<Vec<_>>();
+
+        // Every module must come from a single file, which cannot also be a parent of other
+        // modules, as it would be generated into both `foo.rs` and `foo/mod.rs`.
+        let mut module_files = BTreeMap::new();
+        for (file_path, module_path, _) in &modules {
+            if let Some(other_file_path) = module_files.insert(module_path.as_slice(), *file_path) {
+                return Err(CodeGenError::ModuleConflict(
+                    other_file_path.clone(),
+                    file_path.to_path_buf(),
+                    format!(
+                        "both are generated into module '{}'",
+                        module_path.join("::")
+                    ),
+                ));
+            }
+        }
+
+        for (file_path, module_path, _) in &modules {
+            for depth in 1..module_path.len() {
+                if let Some(parent_file_path) = module_files.get(&module_path[..depth]) {
+                    return Err(CodeGenError::ModuleConflict(
+                        parent_file_path.to_path_buf(),
+                        file_path.to_path_buf(),
+                        format!(
+                            "module '{}' cannot be generated from a file and contain module '{}'",
+                            module_path[..depth].join("::"),
+                            module_path.join("::")
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Find out which module every type is declared in, so that references to types
+        // from other modules can be imported.
+        let mut type_modules = BTreeMap::new();
+        for (file_path, module_path, block) in &modules {
+            let mut type_names = vec![];
+            collect_declared_type_names(block, &mut type_names);
+            for type_name in type_names {
+                let type_name = type_name.to_name_case(opts.type_name_case);
+                if let Some((other_file_path, _)) =
+                    type_modules.insert(type_name.clone(), (*file_path, module_path))
+                    && other_file_path != *file_path
+                {
+                    return Err(CodeGenError::ModuleConflict(
+                        other_file_path.clone(),
+                        file_path.to_path_buf(),
+                        format!("both declare type '{}'", type_name),
+                    ));
+                }
+            }
+        }
+
+        let mut submodules: BTreeMap<Vec<String>, BTreeSet<String>> = BTreeMap::new();
+
+        for (_, module_path, block) in &modules {
+            let mut referenced_type_names = BTreeSet::new();
+            collect_referenced_type_names(opts, block, &mut referenced_type_names);
+
+            let mut writer = IndentedWriter::default();
+            write_header(opts, &mut writer, block);
+
+            let imports = referenced_type_names
+                .iter()
+                .filter_map(|type_name| {
+                    let (_, type_module_path) = type_modules.get(type_name)?;
+                    (*type_module_path != module_path).then(|| {
+                        format!(
+                            "use {}{}::{};",
+                            "super::".repeat(module_path.len()),
+                            type_module_path.join("::"),
+                            type_name
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if !imports.is_empty() {
+                for import in imports {
+                    writer.write_indented_line(import);
+                }
+                writer.next_line();
+            }
+
+            self.visit_block(opts, &mut writer, block)?;
+
+            let file_path = module_path.iter().collect::<PathBuf>().with_extension("rs");
+            files.insert(file_path, writer.into_string());
+
+            for depth in 0..module_path.len() {
+                submodules
+                    .entry(module_path[..depth].to_vec())
+                    .or_default()
+                    .insert(module_path[depth].clone());
+            }
+        }
+
+        for (parent_path, children) in submodules {
+            let mut writer = IndentedWriter::default();
+            writer.write_indented_line(GENERATED_FILE_COMMENT);
+            writer.next_line();
+            for child in children {
+                writer.write_indented_line(format!("pub mod {};", child));
+            }
+
+            let file_path = parent_path.iter().collect::<PathBuf>().join("mod.rs");
+            files.insert(file_path, writer.into_string());
+        }
+
+        Ok(files)
+    }
+}
+
+/// Converts path of a generated file into a path of its module, e.g. `game/PlayerInfo.rs`
+/// becomes `["game", "player_info"]`.
+fn module_path(file_path: &Path) -> Vec<String> {
+    file_path
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_case(Case::Snake))
+        .collect()
 }
 
-fn write_block(
+fn collect_declared_type_names(block: &Block, type_names: &mut Vec<String>) {
+    for node in &block.nodes {
+        match node {
+            BlockNode::Block(block) => collect_declared_type_names(block, type_names),
+            BlockNode::Structure(structure) => type_names.push(structure.name.clone()),
+            BlockNode::Enum(e) => type_names.push(e.name.clone()),
+            BlockNode::Alias(alias) => type_names.push(alias.alias_name.clone()),
+        }
+    }
+}
+
+/// Collects names of all non-builtin types referenced by given block, as they appear in
+/// generated code.
+fn collect_referenced_type_names(
     opts: &CodeGenOptions,
-    writer: &mut IndentedWriter,
     block: &Block,
-    with_brackets: bool,
+    type_names: &mut BTreeSet<String>,
 ) {
-    if with_brackets {
-        writer.write_indented_line("{");
-        writer.push_indent();
+    let mut referenced = vec![];
+
+    for node in &block.nodes {
+        match node {
+            BlockNode::Block(block) => collect_referenced_type_names(opts, block, type_names),
+            BlockNode::Structure(structure) => {
+                referenced.extend(structure.fields.iter().map(|f| f.type_name.token.value()));
+            }
+            BlockNode::Enum(e) => {
+                for structure in e.items.iter().filter_map(|item| item.structure.as_ref()) {
+                    referenced.extend(structure.fields.iter().map(|f| f.type_name.token.value()));
+                }
+            }
+            BlockNode::Alias(alias) => referenced.push(alias.aliased_type_name.clone()),
+        }
+    }
+
+    for type_name in referenced {
+        if BuiltinTypeName::parse(&type_name).is_none() {
+            type_names.insert(type_name.to_name_case(opts.type_name_case));
+        }
+    }
+}
+
+/// Types that can be used in `#[repr(...)]` of enums serialized using `serde_repr`.
+const SERDE_ENUM_REPRS: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// Name of the annotation which adds custom attributes to a declaration, e.g.
+/// `@rust_attr("serde(deny_unknown_fields)")`.
+pub const RUST_ATTRIBUTE_ANNOTATION: &str = "rust_attr";
+
+const GENERATED_FILE_COMMENT: &str =
+    "// This file was generated by NeatProto, do not edit it manually.";
+
+/// Lints that generated code may trigger, e.g. because names are kept as written in the schema.
+const ALLOWED_LINTS: &str = "dead_code, non_camel_case_types, non_snake_case, clippy::all";
+
+pub fn generate_rust(opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+    RustCodeGenerator.generate(opts, root_block)
+}
+
+fn write_header(opts: &CodeGenOptions, writer: &mut IndentedWriter, block: &Block) {
+    writer.write_indented_line(GENERATED_FILE_COMMENT);
+    if opts.rust.with_allow_lints {
+        writer.write_indented_line(format!("#![allow({})]", ALLOWED_LINTS));
+    }
+    writer.next_line();
+
+    if !opts.rust.with_serde || opts.rust.serde_qualified_paths {
+        return;
     }
 
+    let (uses_serde, uses_serde_repr) = find_serde_derives(opts, block);
+    if uses_serde {
+        writer.write_indented_line("use serde::{Deserialize, Serialize};");
+    }
+    if uses_serde_repr {
+        writer.write_indented_line("use serde_repr::{Deserialize_repr, Serialize_repr};");
+    }
+    if uses_serde || uses_serde_repr {
+        writer.next_line();
+    }
+}
+
+/// Returns whether code generated for given block derives serde traits and `serde_repr` traits.
+fn find_serde_derives(opts: &CodeGenOptions, block: &Block) -> (bool, bool) {
+    let mut uses_serde = false;
+    let mut uses_serde_repr = false;
+
     for node in &block.nodes {
         match node {
-            BlockNode::Block(block) => write_block(opts, writer, block, true),
-            BlockNode::Structure(structure) => write_structure(opts, writer, structure),
-            BlockNode::Alias(alias) => write_alias(opts, writer, alias),
-            BlockNode::Enum(e) => write_enum(opts, writer, e),
+            BlockNode::Block(block) => {
+                let (block_uses_serde, block_uses_serde_repr) = find_serde_derives(opts, block);
+                uses_serde |= block_uses_serde;
+                uses_serde_repr |= block_uses_serde_repr;
+            }
+            BlockNode::Structure(_) => uses_serde = true,
+            BlockNode::Enum(e) => {
+                let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
+                if !is_tagged_union && opts.rust.serde_enum_repr.is_some() {
+                    uses_serde_repr = true;
+                } else {
+                    uses_serde = true;
+                }
+            }
+            BlockNode::Alias(_) => {}
         }
     }
 
-    if with_brackets {
-        writer.pop_indent();
-        writer.write_indented_line("}");
+    (uses_serde, uses_serde_repr)
+}
+
+fn serde_derive_attr(opts: &CodeGenOptions) -> &'static str {
+    if opts.rust.serde_qualified_paths {
+        "#[derive(::serde::Serialize, ::serde::Deserialize)]"
+    } else {
+        "#[derive(Serialize, Deserialize)]"
+    }
+}
+
+fn serde_repr_derive_attr(opts: &CodeGenOptions) -> &'static str {
+    if opts.rust.serde_qualified_paths {
+        "#[derive(::serde_repr::Serialize_repr, ::serde_repr::Deserialize_repr)]"
+    } else {
+        "#[derive(Serialize_repr, Deserialize_repr)]"
     }
 }
 
-fn write_structure(opts: &CodeGenOptions, writer: &mut IndentedWriter, structure: &Structure) {
-    write_structure_attributes(opts, writer, structure);
+fn write_structure(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    structure: &Structure,
+) -> Result<(), CodeGenError> {
+    write_structure_attributes(opts, writer, structure)?;
 
     writer.write_indented(format!(
         "pub struct {}",
@@ -54,20 +409,62 @@ fn write_structure(opts: &CodeGenOptions, writer: &mut IndentedWriter, structure
 
     write_structure_body(opts, writer, structure, true);
     writer.next_line();
+    Ok(())
 }
 
 fn write_structure_attributes(
     opts: &CodeGenOptions,
     writer: &mut IndentedWriter,
-    _structure: &Structure,
-) {
+    structure: &Structure,
+) -> Result<(), CodeGenError> {
     writer.write_indented_line("#[derive(Clone)]");
     if opts.rust.with_debug {
         writer.write_indented_line("#[derive(Debug)]");
     }
+    write_extra_derives(opts, writer, structure.properties, &["Clone"]);
     if opts.rust.with_serde {
-        writer.write_indented_line("#[derive(Serialize, Deserialize)]");
-        write_serde_rename_all_attr(opts, writer);
+        writer.write_indented_line(serde_derive_attr(opts));
+        write_serde_rename_all_attr(opts, writer)?;
+    }
+    write_custom_attributes(writer, &structure.annotations);
+    Ok(())
+}
+
+/// Writes derives from the `extra_derives` option which are valid for a type with given
+/// properties, except those in `derived`. Returns names of written derives.
+fn write_extra_derives<'a>(
+    opts: &'a CodeGenOptions,
+    writer: &mut IndentedWriter,
+    properties: TypeProperties,
+    derived: &[&str],
+) -> Vec<&'a str> {
+    let derives = opts
+        .rust
+        .extra_derives
+        .iter()
+        .map(String::as_str)
+        .filter(|derive| !derived.contains(derive))
+        .filter(|derive| !opts.rust.with_debug || *derive != "Debug")
+        .filter(|derive| match *derive {
+            "Eq" | "Hash" | "Ord" => !properties.contains_float,
+            "Default" => !properties.contains_tagged_union,
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    if !derives.is_empty() {
+        writer.write_indented_line(format!("#[derive({})]", derives.join(", ")));
+    }
+
+    derives
+}
+
+/// Writes attributes given using `@rust_attr("...")` annotations, without the `#[]`.
+fn write_custom_attributes(writer: &mut IndentedWriter, annotations: &[Annotation]) {
+    for annotation in Annotation::find_all(annotations, RUST_ATTRIBUTE_ANNOTATION) {
+        for attribute in &annotation.arguments {
+            writer.write_indented_line(format!("#[{}]", attribute));
+        }
     }
 }
 
@@ -99,6 +496,7 @@ fn write_structure_body(
 }
 
 fn write_alias(opts: &CodeGenOptions, writer: &mut IndentedWriter, alias: &Alias) {
+    write_custom_attributes(writer, &alias.annotations);
     writer.write_indented_line(format!(
         "pub type {} = {};",
         alias.alias_name.to_name_case(opts.type_name_case),
@@ -106,11 +504,14 @@ fn write_alias(opts: &CodeGenOptions, writer: &mut IndentedWriter, alias: &Alias
     ));
 }
 
-fn write_enum(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
+fn write_enum(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
     let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
     if is_tagged_union {
-        write_tagged_union(opts, writer, e);
-        return;
+        return write_tagged_union(opts, writer, e);
     }
 
     writer.write_indented_line("#[derive(Clone, Copy, PartialEq, Eq)]");
@@ -119,23 +520,37 @@ fn write_enum(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
         writer.write_indented_line("#[derive(Debug)]");
     }
 
+    let extra_derives = write_extra_derives(
+        opts,
+        writer,
+        e.properties,
+        &["Clone", "Copy", "PartialEq", "Eq"],
+    );
+
     if opts.rust.with_serde {
         if let Some(repr) = &opts.rust.serde_enum_repr {
-            writer.write_indented_line("#[derive(Serialize_repr, Deserialize_repr)]");
+            writer.write_indented_line(serde_repr_derive_attr(opts));
             writer.write_indented_line(format!("#[repr({repr})]"));
         } else {
-            writer.write_indented_line("#[derive(Serialize, Deserialize)]");
+            writer.write_indented_line(serde_derive_attr(opts));
         }
 
-        write_serde_rename_all_attr(opts, writer);
+        write_serde_rename_all_attr(opts, writer)?;
     }
 
+    write_custom_attributes(writer, &e.annotations);
+
     let enum_type_name = e.name.to_name_case(opts.type_name_case);
 
     writer.write_indented_line(format!("pub enum {enum_type_name} {{",));
     writer.push_indent();
 
-    for item in &e.items {
+    for (index, item) in e.items.iter().enumerate() {
+        // Deriving `Default` for enums requires marking the default item.
+        if index == 0 && extra_derives.contains(&"Default") {
+            writer.write_indented_line("#[default]");
+        }
+
         writer.write_indented(item.name.to_name_case(opts.enum_item_name_case));
 
         if let Some(value_token) = &item.value_token {
@@ -180,19 +595,51 @@ impl std::fmt::Display for {enum_type_name} {{
         writer.pop_indent();
         writer.write_indented_line("}");
     }
+
+    Ok(())
 }
 
-fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &Enum) {
+fn write_tagged_union(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
+    writer.write_indented_line("#[derive(Clone)]");
     if opts.rust.with_debug {
         writer.write_indented_line("#[derive(Debug)]");
     }
 
+    write_extra_derives(opts, writer, e.properties, &["Clone"]);
+
     if opts.rust.with_serde {
-        writer.write_indented_line("#[derive(Serialize, Deserialize)]");
-        writer.write_indented_line("#[serde(tag = \"kind\", content = \"value\")]");
-        write_serde_rename_all_attr(opts, writer);
+        let layout = TaggedUnionLayout::resolve(opts, e)?;
+
+        writer.write_indented_line(serde_derive_attr(opts));
+        match layout.repr {
+            TaggedUnionRepr::Internal => {
+                writer.write_indented_line(format!("#[serde(tag = \"{}\")]", layout.tag))
+            }
+            TaggedUnionRepr::Adjacent => writer.write_indented_line(format!(
+                "#[serde(tag = \"{}\", content = \"{}\")]",
+                layout.tag, layout.content
+            )),
+            TaggedUnionRepr::External => {}
+            TaggedUnionRepr::Untagged => writer.write_indented_line("#[serde(untagged)]"),
+        }
+
+        // Items are renamed the same way as items of plain enums, and their fields the same
+        // way as fields of structures.
+        write_serde_rename_all_attr(opts, writer)?;
+        if let NameCase::Other(case) = opts.rust.serde_struct_field_name_case {
+            writer.write_indented_line(format!(
+                "#[serde(rename_all_fields = \"{}\")]",
+                serde_rename_case(&case)?
+            ));
+        }
     }
 
+    write_custom_attributes(writer, &e.annotations);
+
     writer.write_indented_line(format!(
         "pub enum {} {{",
         e.name.to_name_case(opts.type_name_case)
@@ -200,10 +647,6 @@ fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &En
     writer.push_indent();
 
     for item in &e.items {
-        if opts.rust.with_serde {
-            write_serde_rename_all_attr(opts, writer);
-        }
-
         writer.write_indented(item.name.to_name_case(opts.enum_item_name_case));
 
         if let Some(structure) = &item.structure {
@@ -215,15 +658,30 @@ fn write_tagged_union(opts: &CodeGenOptions, writer: &mut IndentedWriter, e: &En
 
     writer.pop_indent();
     writer.write_indented_line("}");
+    Ok(())
 }
 
-fn write_serde_rename_all_attr(opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+fn write_serde_rename_all_attr(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+) -> Result<(), CodeGenError> {
     if let NameCase::Other(case) = opts.rust.serde_struct_field_name_case {
         writer.write_indented_line(format!(
             "#[serde(rename_all = \"{}\")]",
-            map_case_to_serde(&case).expect("invalid `serde_struct_field_name_case`")
+            serde_rename_case(&case)?
         ));
     }
+    Ok(())
+}
+
+/// Returns the value of `#[serde(rename_all = ...)]` for given case of serialized names.
+fn serde_rename_case(case: &Case) -> Result<&'static str, CodeGenError> {
+    map_case_to_serde(case).ok_or_else(|| {
+        CodeGenError::InvalidOption(
+            "serde_struct_field_name_case".to_string(),
+            format!("{:?} is not supported by `#[serde(rename_all)]`", case),
+        )
+    })
 }
 
 /// Maps `Case` enum to a value that `#[serde(rename_all = ???)]` supports.
@@ -276,3 +734,711 @@ fn translate_builtin_type_name(type_name: BuiltinTypeName) -> &'static str {
         BuiltinTypeName::String => "String",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::parse;
+    use neatproto_analysis::analyze_block;
+
+    /// Derives depend on properties of types, which are computed by the analysis.
+    fn generate(opts: &CodeGenOptions, source: &str) -> String {
+        let mut block = parse(source);
+        analyze_block(&mut block).expect("failed to analyze");
+        generate_rust(opts, &block).expect("failed to generate code")
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let opts = CodeGenOptions::default();
+        let mut block = parse("alias B = A;");
+        block
+            .nodes
+            .insert(0, BlockNode::Block(parse("struct A { x: bool; }")));
+
+        assert_eq!(
+            generate_rust(&opts, &block).expect("failed to generate code"),
+            r#"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+{
+    #[derive(Clone)]
+    pub struct A {
+        pub x: bool,
+    }
+}
+pub type B = A;
+"#
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let block = parse("@repr(\"unknown\") enum Shape { Circle { r: float; }, Empty }");
+        assert!(matches!(
+            generate_rust(&opts, &block),
+            Err(CodeGenError::InvalidAnnotation(..))
+        ));
+
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                serde_struct_field_name_case: NameCase::Other(Case::Title),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_rust(&opts, &parse("struct A { x: bool; }")),
+            Err(CodeGenError::InvalidOption(..))
+        ));
+    }
+
+    fn generate_modules(
+        opts: &CodeGenOptions,
+        modules: &[(&str, &str)],
+    ) -> Result<BTreeMap<PathBuf, String>, CodeGenError> {
+        let modules = modules
+            .iter()
+            .map(|(file_path, source)| (PathBuf::from(file_path), parse(source)))
+            .collect::<Vec<_>>();
+        RustCodeGenerator.generate_modules(opts, &modules)
+    }
+
+    #[test]
+    fn test_module_tree() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                module_tree: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let files = generate_modules(
+            &opts,
+            &[
+                ("a.rs", "struct A { x: bool; }"),
+                ("game/PlayerInfo.rs", "struct Player { a: A; }"),
+            ],
+        )
+        .expect("failed to generate code");
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            ["a.rs", "game/mod.rs", "game/player_info.rs", "mod.rs"]
+        );
+        assert_eq!(
+            files[Path::new("game/player_info.rs")],
+            r#"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use super::super::a::A;
+
+#[derive(Clone)]
+pub struct Player {
+    pub a: A,
+}
+"#
+        );
+        assert_eq!(
+            files[Path::new("game/mod.rs")],
+            r#"// This file was generated by NeatProto, do not edit it manually.
+
+pub mod player_info;
+"#
+        );
+        assert_eq!(
+            files[Path::new("mod.rs")],
+            r#"// This file was generated by NeatProto, do not edit it manually.
+
+pub mod a;
+pub mod game;
+"#
+        );
+    }
+
+    #[test]
+    fn test_module_tree_conflicts() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                module_tree: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        for modules in [
+            [
+                ("foo.rs", "struct A { x: bool; }"),
+                ("foo/bar.rs", "struct B { x: bool; }"),
+            ],
+            [
+                ("Foo.rs", "struct A { x: bool; }"),
+                ("foo.rs", "struct B { x: bool; }"),
+            ],
+            [
+                ("a.rs", "struct A { x: bool; }"),
+                ("b.rs", "struct A { y: bool; }"),
+            ],
+        ] {
+            assert!(matches!(
+                generate_modules(&opts, &modules),
+                Err(CodeGenError::ModuleConflict(..))
+            ));
+        }
+
+        let opts = CodeGenOptions::default();
+        assert!(
+            generate_modules(
+                &opts,
+                &[
+                    ("a.rs", "struct A { x: bool; }"),
+                    ("b.rs", "struct A { y: bool; }")
+                ],
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_serde_imports() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Player { name: string; }
+enum Color { Red = 1, Green }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub enum Color {
+    Red = 1,
+    Green,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_serde_qualified_paths() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                serde_qualified_paths: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Player { name: string; }
+enum Color { Red = 1, Green }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+#[derive(Clone)]
+#[derive(::serde::Serialize, ::serde::Deserialize)]
+pub struct Player {
+    pub name: String,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(::serde::Serialize, ::serde::Deserialize)]
+pub enum Color {
+    Red = 1,
+    Green,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_serde_repr_imports() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                serde_enum_repr: Some("u8".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Player { name: string; }
+enum Color { Red = 1, Green }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum Color {
+    Red = 1,
+    Green,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_serde_repr_qualified_paths() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                serde_enum_repr: Some("u8".to_string()),
+                serde_qualified_paths: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Player { name: string; }
+enum Color { Red = 1, Green }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+#[derive(Clone)]
+#[derive(::serde::Serialize, ::serde::Deserialize)]
+pub struct Player {
+    pub name: String,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(::serde_repr::Serialize_repr, ::serde_repr::Deserialize_repr)]
+#[repr(u8)]
+pub enum Color {
+    Red = 1,
+    Green,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_without_allow_lints() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                with_allow_lints: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Player { name: string; }"),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_serde_repr_unused() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                serde_enum_repr: Some("u8".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Player { name: string; }
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+}
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_extra_derives() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                extra_derives: vec![
+                    "PartialEq".to_string(),
+                    "Eq".to_string(),
+                    "Hash".to_string(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Id { value: uint64; }
+struct Point { x: float; y: float; }
+struct Player { id: Id; position: Point; }
+enum Color { Red, Green }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+#[derive(Clone)]
+#[derive(PartialEq, Eq, Hash)]
+pub struct Id {
+    pub value: u64,
+}
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Player {
+    pub id: Id,
+    pub position: Point,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Hash)]
+pub enum Color {
+    Red,
+    Green,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_extra_derives_tagged_union() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_debug: true,
+                extra_derives: vec![
+                    "PartialEq".to_string(),
+                    "Eq".to_string(),
+                    "Hash".to_string(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"struct Point { x: float; }
+enum Command { Jump { height: uint32; }, Stop }
+enum Shape { Circle { center: Point; }, Empty }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Point {
+    pub x: f32,
+}
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq, Eq, Hash)]
+pub enum Command {
+    Jump {
+        height: u32,
+    },
+    Stop,
+}
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum Shape {
+    Circle {
+        center: Point,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_rust_attr_annotations() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                with_serde: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"@rust_attr("serde(deny_unknown_fields)")
+struct Player { name: string; }
+@rust_attr("non_exhaustive") @rust_attr("allow(clippy::enum_variant_names)")
+enum Color { Red, Green }
+@rust_attr("non_exhaustive")
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Player {
+    pub name: String,
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[non_exhaustive]
+#[allow(clippy::enum_variant_names)]
+pub enum Color {
+    Red,
+    Green,
+}
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+#[non_exhaustive]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_adjacent() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Adjacent,
+            rust: RustCodeGenOptions {
+                with_debug: true,
+                with_serde: true,
+                extra_derives: vec!["PartialEq".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Shape { Circle { r: float; }, Empty }"),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_internal() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Internal,
+            rust: RustCodeGenOptions {
+                with_debug: true,
+                with_serde: true,
+                extra_derives: vec!["PartialEq".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Shape { Circle { r: float; }, Empty }"),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_external() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::External,
+            rust: RustCodeGenOptions {
+                with_debug: true,
+                with_serde: true,
+                extra_derives: vec!["PartialEq".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Shape { Circle { r: float; }, Empty }"),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_untagged() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Untagged,
+            rust: RustCodeGenOptions {
+                with_debug: true,
+                with_serde: true,
+                extra_derives: vec!["PartialEq".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Shape { Circle { r: float; }, Empty }"),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_annotations() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Internal,
+            rust: RustCodeGenOptions {
+                with_debug: true,
+                with_serde: true,
+                extra_derives: vec!["PartialEq".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                r#"@repr(adjacent) @tag("type") @content("data")
+enum Shape { Circle { r: float; }, Empty }"#,
+            ),
+            r##"// This file was generated by NeatProto, do not edit it manually.
+#![allow(dead_code, non_camel_case_types, non_snake_case, clippy::all)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Shape {
+    Circle {
+        r: f32,
+    },
+    Empty,
+}
+"##
+        );
+    }
+}
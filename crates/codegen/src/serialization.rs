@@ -0,0 +1,38 @@
+//! Helpers describing the JSON produced by the Rust target, used by other targets which have
+//! to read and write the same representation.
+
+use crate::{CodeGenOptions, NameCase, NameCasing};
+use convert_case::Casing;
+use neatproto_ast::Enum;
+
+/// Returns the name under which a field or an enum item appears in JSON produced by the Rust
+/// target, i.e. after `#[serde(rename_all = ...)]` has been applied.
+pub(crate) fn serialized_name(opts: &CodeGenOptions, name: &String, case: NameCase) -> String {
+    match opts.rust.serde_struct_field_name_case {
+        NameCase::Other(serde_case) => name.to_case(serde_case),
+        NameCase::Unchanged => name.to_name_case(case),
+    }
+}
+
+/// Returns discriminants of items of a plain enum, which the Rust target serializes instead
+/// of item names when `serde_enum_repr` is set. Items without an explicit value follow the
+/// previous item, like in Rust.
+pub(crate) fn enum_discriminants(e: &Enum) -> Vec<i128> {
+    let mut next_value = 0i128;
+    e.items
+        .iter()
+        .map(|item| {
+            if let Some(value) = item
+                .value_token
+                .as_ref()
+                .and_then(|token| token.value().parse::<i128>().ok())
+            {
+                next_value = value;
+            }
+
+            let value = next_value;
+            next_value += 1;
+            value
+        })
+        .collect()
+}
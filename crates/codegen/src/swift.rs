@@ -0,0 +1,811 @@
+use crate::serialization::{enum_discriminants, serialized_name};
+use crate::tagged_union::tag_value;
+use crate::writer::IndentedWriter;
+use crate::{
+    CodeGenError, CodeGenOptions, CodeGenerator, NameCasing, TaggedUnionLayout, TaggedUnionRepr,
+};
+use convert_case::{Case, Casing};
+use neatproto_ast::*;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwiftCodeGenOptions {
+    pub access_modifier: String,
+}
+
+impl Default for SwiftCodeGenOptions {
+    fn default() -> Self {
+        Self {
+            access_modifier: "public".into(),
+        }
+    }
+}
+
+pub struct SwiftCodeGenerator;
+
+impl CodeGenerator for SwiftCodeGenerator {
+    fn file_extension(&self) -> &str {
+        "swift"
+    }
+
+    fn tagged_union_reprs(&self, _opts: &CodeGenOptions) -> &'static [TaggedUnionRepr] {
+        &[TaggedUnionRepr::Internal, TaggedUnionRepr::Adjacent]
+    }
+
+    fn write_header(&self, _opts: &CodeGenOptions, writer: &mut IndentedWriter) {
+        writer.write_line("import Foundation");
+        writer.next_line();
+    }
+
+    fn visit_structure(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        structure: &Structure,
+    ) -> Result<(), CodeGenError> {
+        write_structure(opts, writer, structure);
+        Ok(())
+    }
+
+    fn visit_enum(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        e: &Enum,
+    ) -> Result<(), CodeGenError> {
+        write_enum(opts, writer, e)
+    }
+
+    fn visit_alias(
+        &self,
+        opts: &CodeGenOptions,
+        writer: &mut IndentedWriter,
+        alias: &Alias,
+    ) -> Result<(), CodeGenError> {
+        write_alias(opts, writer, alias);
+        Ok(())
+    }
+}
+
+pub fn generate_swift(opts: &CodeGenOptions, root_block: &Block) -> Result<String, CodeGenError> {
+    SwiftCodeGenerator.generate(opts, root_block)
+}
+
+fn access_modifier(opts: &CodeGenOptions) -> String {
+    if opts.swift.access_modifier.is_empty() {
+        String::new()
+    } else {
+        format!("{} ", opts.swift.access_modifier)
+    }
+}
+
+fn write_structure(opts: &CodeGenOptions, writer: &mut IndentedWriter, structure: &Structure) {
+    write_struct(
+        opts,
+        writer,
+        &structure.name.to_name_case(opts.type_name_case),
+        structure,
+    );
+    writer.next_line();
+}
+
+fn write_struct(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    struct_name: &str,
+    structure: &Structure,
+) {
+    let access = access_modifier(opts);
+
+    writer.write_indented_line(format!("{access}struct {struct_name}: Codable {{"));
+    writer.push_indent();
+
+    let fields = structure
+        .fields
+        .iter()
+        .map(|field| {
+            (
+                field.name.to_name_case(opts.field_name_case),
+                get_full_type_name(opts, &field.type_name),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for (field_name, field_type_name) in &fields {
+        writer.write_indented_line(format!("{access}var {field_name}: {field_type_name}"));
+    }
+
+    // Memberwise initializers synthesized by the compiler are always internal, so they
+    // have to be written out in order to construct the structure from another module.
+    if !fields.is_empty() {
+        writer.next_line();
+    }
+
+    let init_params = fields
+        .iter()
+        .map(|(field_name, field_type_name)| format!("{field_name}: {field_type_name}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    writer.write_indented_line(format!("{access}init({init_params}) {{"));
+    writer.push_indent();
+
+    for (field_name, _) in &fields {
+        writer.write_indented_line(format!("self.{field_name} = {field_name}"));
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+
+    // Keys are only declared when fields are serialized under different names than their
+    // own, otherwise the synthesized ones are used.
+    let keys = structure
+        .fields
+        .iter()
+        .zip(&fields)
+        .map(|(field, (field_name, _))| {
+            coding_key(
+                field_name,
+                &serialized_name(opts, &field.name, opts.field_name_case),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if keys.iter().any(|key| key.contains('=')) {
+        writer.next_line();
+        writer.write_indented_line("private enum CodingKeys: String, CodingKey {");
+        writer.push_indent();
+        for key in keys {
+            writer.write_indented_line(key);
+        }
+        writer.pop_indent();
+        writer.write_indented_line("}");
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+}
+
+fn write_alias(opts: &CodeGenOptions, writer: &mut IndentedWriter, alias: &Alias) {
+    writer.write_indented_line(format!(
+        "{}typealias {} = {}",
+        access_modifier(opts),
+        alias.alias_name.to_name_case(opts.type_name_case),
+        translate_type_name(opts, &alias.aliased_type_name)
+    ));
+    writer.next_line();
+}
+
+fn write_enum(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
+    let is_tagged_union = e.items.iter().any(|item| item.structure.is_some());
+    if is_tagged_union {
+        return write_tagged_union(opts, writer, e);
+    }
+
+    // Mirror the Rust target: enums are (de)serialized as their values when `serde_enum_repr`
+    // is set, and as item names otherwise.
+    let with_values = opts.rust.serde_enum_repr.is_some();
+    let raw_type_name = if with_values { "Int" } else { "String" };
+
+    writer.write_indented_line(format!(
+        "{}enum {}: {raw_type_name}, Codable {{",
+        access_modifier(opts),
+        e.name.to_name_case(opts.type_name_case)
+    ));
+    writer.push_indent();
+
+    for (item, value) in e.items.iter().zip(enum_discriminants(e)) {
+        let case_name = item.name.to_name_case(opts.enum_item_name_case);
+        if with_values {
+            writer.write_indented_line(format!("case {case_name} = {value}"));
+        } else {
+            writer.write_indented_line(coding_key(
+                &case_name,
+                &serialized_name(opts, &item.name, opts.enum_item_name_case),
+            ));
+        }
+    }
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.next_line();
+    Ok(())
+}
+
+/// Tagged unions are written as enums with associated values. Each case carries a nested
+/// structure holding its fields, and a custom `Codable` implementation reads and writes either
+/// the `{ "kind": "...", "value": { ... } }` layout used by the other targets, or the fields
+/// next to the tag when the union is internally tagged.
+fn write_tagged_union(
+    opts: &CodeGenOptions,
+    writer: &mut IndentedWriter,
+    e: &Enum,
+) -> Result<(), CodeGenError> {
+    let access = access_modifier(opts);
+    let layout = TaggedUnionLayout::resolve(opts, e)?;
+    let is_internally_tagged = layout.repr == TaggedUnionRepr::Internal;
+
+    writer.write_indented_line(format!(
+        "{access}enum {}: Codable {{",
+        e.name.to_name_case(opts.type_name_case)
+    ));
+    writer.push_indent();
+
+    let case_names = tagged_union_case_names(opts, e);
+    for (item, (case_name, struct_name)) in e.items.iter().zip(&case_names) {
+        match &item.structure {
+            Some(_) => writer.write_indented_line(format!("case {case_name}({struct_name})")),
+            None => writer.write_indented_line(format!("case {case_name}")),
+        }
+    }
+
+    for (item, (_, struct_name)) in e.items.iter().zip(&case_names) {
+        if let Some(structure) = &item.structure {
+            writer.next_line();
+            write_struct(opts, writer, struct_name, structure);
+        }
+    }
+
+    writer.next_line();
+    writer.write_indented_line("private enum CodingKeys: String, CodingKey {");
+    writer.push_indent();
+    writer.write_indented_line(coding_key("kind", layout.tag));
+    if !is_internally_tagged {
+        writer.write_indented_line(coding_key("value", layout.content));
+    }
+    writer.pop_indent();
+    writer.write_indented_line("}");
+
+    // --
+    // Decoding.
+    writer.next_line();
+    writer.write_indented_line(format!("{access}init(from decoder: Decoder) throws {{"));
+    writer.push_indent();
+    writer.write_indented_line("let container = try decoder.container(keyedBy: CodingKeys.self)");
+    writer.write_indented_line("let kind = try container.decode(String.self, forKey: .kind)");
+    writer.write_indented_line("switch kind {");
+
+    for (item, (case_name, struct_name)) in e.items.iter().zip(&case_names) {
+        writer.write_indented_line(format!("case \"{}\":", tag_value(opts, item)));
+        writer.push_indent();
+        match &item.structure {
+            Some(_) if is_internally_tagged => writer.write_indented_line(format!(
+                "self = .{case_name}(try {struct_name}(from: decoder))"
+            )),
+            Some(_) => writer.write_indented_line(format!(
+                "self = .{case_name}(try container.decode({struct_name}.self, forKey: .value))"
+            )),
+            None => writer.write_indented_line(format!("self = .{case_name}")),
+        }
+        writer.pop_indent();
+    }
+
+    writer.write_indented_line("default:");
+    writer.push_indent();
+    writer.write_indented_line("throw DecodingError.dataCorruptedError(");
+    writer.push_indent();
+    writer.write_indented_line("forKey: .kind,");
+    writer.write_indented_line("in: container,");
+    writer.write_indented_line("debugDescription: \"Unknown kind: \\(kind)\"");
+    writer.pop_indent();
+    writer.write_indented_line(")");
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.pop_indent();
+    writer.write_indented_line("}");
+
+    // --
+    // Encoding.
+    writer.next_line();
+    writer.write_indented_line(format!(
+        "{access}func encode(to encoder: Encoder) throws {{"
+    ));
+    writer.push_indent();
+    writer.write_indented_line("var container = encoder.container(keyedBy: CodingKeys.self)");
+    writer.write_indented_line("switch self {");
+
+    for (item, (case_name, _)) in e.items.iter().zip(&case_names) {
+        match &item.structure {
+            Some(_) => writer.write_indented_line(format!("case .{case_name}(let value):")),
+            None => writer.write_indented_line(format!("case .{case_name}:")),
+        }
+        writer.push_indent();
+        writer.write_indented_line(format!(
+            "try container.encode(\"{}\", forKey: .kind)",
+            tag_value(opts, item)
+        ));
+        if item.structure.is_some() {
+            if is_internally_tagged {
+                writer.write_indented_line("try value.encode(to: encoder)");
+            } else {
+                writer.write_indented_line("try container.encode(value, forKey: .value)");
+            }
+        }
+        writer.pop_indent();
+    }
+
+    writer.write_indented_line("}");
+    writer.pop_indent();
+    writer.write_indented_line("}");
+
+    writer.pop_indent();
+    writer.write_indented_line("}");
+    writer.next_line();
+    Ok(())
+}
+
+/// Returns names of cases of a tagged union and of structures holding their fields. Swift does
+/// not allow a case and a nested type with the same name, so if any case would clash with its
+/// structure, all cases are written in camel case instead (and structures in pascal case, if
+/// types are named in camel case too).
+fn tagged_union_case_names(opts: &CodeGenOptions, e: &Enum) -> Vec<(String, String)> {
+    let names = e
+        .items
+        .iter()
+        .map(|item| {
+            (
+                item.name.to_name_case(opts.enum_item_name_case),
+                item.name.to_name_case(opts.type_name_case),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let clashes = |names: &[(String, String)]| {
+        e.items
+            .iter()
+            .zip(names)
+            .any(|(item, (case_name, struct_name))| {
+                item.structure.is_some() && case_name == struct_name
+            })
+    };
+
+    if !clashes(&names) {
+        return names;
+    }
+
+    let names = e
+        .items
+        .iter()
+        .zip(names)
+        .map(|(item, (_, struct_name))| (item.name.to_case(Case::Camel), struct_name))
+        .collect::<Vec<_>>();
+
+    if !clashes(&names) {
+        return names;
+    }
+
+    e.items
+        .iter()
+        .map(|item| {
+            (
+                item.name.to_case(Case::Camel),
+                item.name.to_case(Case::Pascal),
+            )
+        })
+        .collect()
+}
+
+/// Returns declaration of a coding key case, with a raw value if the name of the key differs
+/// from the name of the case.
+fn coding_key(case_name: &str, key: &str) -> String {
+    if case_name == key {
+        format!("case {case_name}")
+    } else {
+        format!("case {case_name} = \"{key}\"")
+    }
+}
+
+fn get_full_type_name(opts: &CodeGenOptions, type_name: &TypeName) -> String {
+    let name = translate_type_name(opts, &type_name.token.value());
+    if type_name.is_array {
+        return format!("[{}]", name);
+    }
+    name
+}
+
+fn translate_type_name(opts: &CodeGenOptions, type_name: &String) -> String {
+    BuiltinTypeName::parse(type_name)
+        .map(|t| translate_builtin_type_name(t).to_string())
+        .unwrap_or_else(|| type_name.to_name_case(opts.type_name_case))
+}
+
+fn translate_builtin_type_name(type_name: BuiltinTypeName) -> &'static str {
+    match type_name {
+        BuiltinTypeName::Uint8 => "UInt8",
+        BuiltinTypeName::Uint16 => "UInt16",
+        BuiltinTypeName::Uint32 => "UInt32",
+        BuiltinTypeName::Uint64 => "UInt64",
+        BuiltinTypeName::Uint128 => "UInt128",
+        BuiltinTypeName::Int8 => "Int8",
+        BuiltinTypeName::Int16 => "Int16",
+        BuiltinTypeName::Int32 => "Int32",
+        BuiltinTypeName::Int64 => "Int64",
+        BuiltinTypeName::Int128 => "Int128",
+        BuiltinTypeName::Float32 => "Float",
+        BuiltinTypeName::Float64 => "Double",
+        BuiltinTypeName::Bool => "Bool",
+        BuiltinTypeName::String => "String",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::swift::generate_swift;
+    use crate::tests::parse;
+    use crate::{Case, CodeGenOptions, NameCase, RustCodeGenOptions, SwiftCodeGenOptions};
+
+    fn generate(opts: &CodeGenOptions, source: &str) -> String {
+        generate_swift(opts, &parse(source)).expect("failed to generate code")
+    }
+
+    #[test]
+    fn test_structure() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                "alias Id = uint64; struct Point { x: float; tags: string[]; id: Id; } enum Color { Red, Green }",
+            ),
+            r##"import Foundation
+
+public typealias Id = UInt64
+
+public struct Point: Codable {
+    public var x: Float
+    public var tags: [String]
+    public var id: Id
+
+    public init(x: Float, tags: [String], id: Id) {
+        self.x = x
+        self.tags = tags
+        self.id = id
+    }
+}
+
+public enum Color: String, Codable {
+    case Red
+    case Green
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_access_modifier() {
+        let opts = CodeGenOptions {
+            swift: SwiftCodeGenOptions {
+                access_modifier: String::new(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "struct Point { x: float; }"),
+            r##"import Foundation
+
+struct Point: Codable {
+    var x: Float
+
+    init(x: Float) {
+        self.x = x
+    }
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_enum_with_values_serialized_as_names() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(&opts, "enum Color { Red = 1, Green, Blue = 5 }"),
+            r##"import Foundation
+
+public enum Color: String, Codable {
+    case Red
+    case Green
+    case Blue
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_enum_with_values_serialized_as_values() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                serde_enum_repr: Some("u8".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&opts, "enum Color { Red = 1, Green, Blue = 5 }"),
+            r##"import Foundation
+
+public enum Color: Int, Codable {
+    case Red = 1
+    case Green = 2
+    case Blue = 5
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let opts = CodeGenOptions {
+            rust: RustCodeGenOptions {
+                serde_struct_field_name_case: NameCase::Other(Case::Camel),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                "struct Point { pos_x: float; } enum Color { light_red, dark_blue } enum Shape { big_circle { center_point: Point; }, empty }",
+            ),
+            r##"import Foundation
+
+public struct Point: Codable {
+    public var pos_x: Float
+
+    public init(pos_x: Float) {
+        self.pos_x = pos_x
+    }
+
+    private enum CodingKeys: String, CodingKey {
+        case pos_x = "posX"
+    }
+}
+
+public enum Color: String, Codable {
+    case light_red = "lightRed"
+    case dark_blue = "darkBlue"
+}
+
+public enum Shape: Codable {
+    case bigCircle(big_circle)
+    case empty
+
+    public struct big_circle: Codable {
+        public var center_point: Point
+
+        public init(center_point: Point) {
+            self.center_point = center_point
+        }
+
+        private enum CodingKeys: String, CodingKey {
+            case center_point = "centerPoint"
+        }
+    }
+
+    private enum CodingKeys: String, CodingKey {
+        case kind
+        case value
+    }
+
+    public init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let kind = try container.decode(String.self, forKey: .kind)
+        switch kind {
+        case "bigCircle":
+            self = .bigCircle(try container.decode(big_circle.self, forKey: .value))
+        case "empty":
+            self = .empty
+        default:
+            throw DecodingError.dataCorruptedError(
+                forKey: .kind,
+                in: container,
+                debugDescription: "Unknown kind: \(kind)"
+            )
+        }
+    }
+
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: CodingKeys.self)
+        switch self {
+        case .bigCircle(let value):
+            try container.encode("bigCircle", forKey: .kind)
+            try container.encode(value, forKey: .value)
+        case .empty:
+            try container.encode("empty", forKey: .kind)
+        }
+    }
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                r#"@tag("type") @content("data") enum Shape { Circle { radius: float; }, Empty }"#,
+            ),
+            r##"import Foundation
+
+public enum Shape: Codable {
+    case circle(Circle)
+    case empty
+
+    public struct Circle: Codable {
+        public var radius: Float
+
+        public init(radius: Float) {
+            self.radius = radius
+        }
+    }
+
+    private enum CodingKeys: String, CodingKey {
+        case kind = "type"
+        case value = "data"
+    }
+
+    public init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let kind = try container.decode(String.self, forKey: .kind)
+        switch kind {
+        case "Circle":
+            self = .circle(try container.decode(Circle.self, forKey: .value))
+        case "Empty":
+            self = .empty
+        default:
+            throw DecodingError.dataCorruptedError(
+                forKey: .kind,
+                in: container,
+                debugDescription: "Unknown kind: \(kind)"
+            )
+        }
+    }
+
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: CodingKeys.self)
+        switch self {
+        case .circle(let value):
+            try container.encode("Circle", forKey: .kind)
+            try container.encode(value, forKey: .value)
+        case .empty:
+            try container.encode("Empty", forKey: .kind)
+        }
+    }
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_internally_tagged_union() {
+        let opts = CodeGenOptions::default();
+        assert_eq!(
+            generate(
+                &opts,
+                "@repr(internal) enum Shape { Circle { radius: float; }, Empty }",
+            ),
+            r##"import Foundation
+
+public enum Shape: Codable {
+    case circle(Circle)
+    case empty
+
+    public struct Circle: Codable {
+        public var radius: Float
+
+        public init(radius: Float) {
+            self.radius = radius
+        }
+    }
+
+    private enum CodingKeys: String, CodingKey {
+        case kind
+    }
+
+    public init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let kind = try container.decode(String.self, forKey: .kind)
+        switch kind {
+        case "Circle":
+            self = .circle(try Circle(from: decoder))
+        case "Empty":
+            self = .empty
+        default:
+            throw DecodingError.dataCorruptedError(
+                forKey: .kind,
+                in: container,
+                debugDescription: "Unknown kind: \(kind)"
+            )
+        }
+    }
+
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: CodingKeys.self)
+        switch self {
+        case .circle(let value):
+            try container.encode("Circle", forKey: .kind)
+            try value.encode(to: encoder)
+        case .empty:
+            try container.encode("Empty", forKey: .kind)
+        }
+    }
+}
+
+"##
+        );
+    }
+
+    #[test]
+    fn test_tagged_union_name_cases() {
+        let opts = CodeGenOptions {
+            type_name_case: NameCase::Other(Case::Pascal),
+            enum_item_name_case: NameCase::Other(Case::Snake),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(
+                &opts,
+                "enum shape_kind { big_circle { radius: float; }, empty }",
+            ),
+            r##"import Foundation
+
+public enum ShapeKind: Codable {
+    case big_circle(BigCircle)
+    case empty
+
+    public struct BigCircle: Codable {
+        public var radius: Float
+
+        public init(radius: Float) {
+            self.radius = radius
+        }
+    }
+
+    private enum CodingKeys: String, CodingKey {
+        case kind
+        case value
+    }
+
+    public init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let kind = try container.decode(String.self, forKey: .kind)
+        switch kind {
+        case "big_circle":
+            self = .big_circle(try container.decode(BigCircle.self, forKey: .value))
+        case "empty":
+            self = .empty
+        default:
+            throw DecodingError.dataCorruptedError(
+                forKey: .kind,
+                in: container,
+                debugDescription: "Unknown kind: \(kind)"
+            )
+        }
+    }
+
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: CodingKeys.self)
+        switch self {
+        case .big_circle(let value):
+            try container.encode("big_circle", forKey: .kind)
+            try container.encode(value, forKey: .value)
+        case .empty:
+            try container.encode("empty", forKey: .kind)
+        }
+    }
+}
+
+"##
+        );
+    }
+}
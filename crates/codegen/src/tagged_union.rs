@@ -0,0 +1,323 @@
+use crate::serialization::serialized_name;
+use crate::{CodeGenError, CodeGenOptions};
+use neatproto_ast::{Annotation, Block, BlockNode, Enum, EnumItem};
+
+/// Annotation overriding the representation of a single tagged union, e.g. `@repr("internal")`.
+pub const REPR_ANNOTATION: &str = "repr";
+
+/// Annotation changing the name of the field holding the item name, e.g. `@tag("type")`.
+pub const TAG_ANNOTATION: &str = "tag";
+
+/// Annotation changing the name of the field holding fields of adjacently tagged items,
+/// e.g. `@content("data")`.
+pub const CONTENT_ANNOTATION: &str = "content";
+
+pub const DEFAULT_TAG_FIELD_NAME: &str = "kind";
+pub const DEFAULT_CONTENT_FIELD_NAME: &str = "value";
+
+/// JSON representation of tagged unions, named after serde's enum representations. Since all
+/// targets have to agree on it, it is shared by all of them, and targets which can not
+/// represent some of the layouts reject unions using them.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaggedUnionRepr {
+    /// `{ "kind": "...", ...fields }`
+    Internal,
+    /// `{ "kind": "...", "value": { ...fields } }`
+    #[default]
+    Adjacent,
+    /// `{ "...": { ...fields } }`
+    External,
+    /// `{ ...fields }`, where the case is inferred from the fields.
+    Untagged,
+}
+
+impl TaggedUnionRepr {
+    pub const ALL: [TaggedUnionRepr; 4] = [
+        TaggedUnionRepr::Internal,
+        TaggedUnionRepr::Adjacent,
+        TaggedUnionRepr::External,
+        TaggedUnionRepr::Untagged,
+    ];
+
+    /// Name of the representation, as accepted on the command line and in config files.
+    pub fn name(&self) -> String {
+        clap::ValueEnum::to_possible_value(self)
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as clap::ValueEnum>::from_str(name, true).ok()
+    }
+
+    pub fn has_tag(&self) -> bool {
+        matches!(self, TaggedUnionRepr::Internal | TaggedUnionRepr::Adjacent)
+    }
+}
+
+/// JSON layout of a single tagged union, resolved from its annotations and options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaggedUnionLayout<'a> {
+    pub repr: TaggedUnionRepr,
+    /// Name of the field holding the item name, unused by external and untagged unions.
+    pub tag: &'a str,
+    /// Name of the field holding fields of the item, used only by adjacently tagged unions.
+    pub content: &'a str,
+}
+
+impl<'a> TaggedUnionLayout<'a> {
+    pub fn resolve(opts: &CodeGenOptions, e: &'a Enum) -> Result<Self, CodeGenError> {
+        let repr = match single_argument(e, REPR_ANNOTATION)? {
+            Some(name) => TaggedUnionRepr::from_name(name).ok_or_else(|| {
+                invalid_annotation(
+                    e,
+                    REPR_ANNOTATION,
+                    format!("unknown tagged union representation '{}'", name),
+                )
+            })?,
+            None => opts.tagged_union_repr,
+        };
+
+        let tag = single_argument(e, TAG_ANNOTATION)?;
+        if tag.is_some() && !repr.has_tag() {
+            return Err(invalid_annotation(
+                e,
+                TAG_ANNOTATION,
+                format!("'{}' tagged unions have no tag field", repr.name()),
+            ));
+        }
+
+        let content = single_argument(e, CONTENT_ANNOTATION)?;
+        if content.is_some() && repr != TaggedUnionRepr::Adjacent {
+            return Err(invalid_annotation(
+                e,
+                CONTENT_ANNOTATION,
+                format!("'{}' tagged unions have no content field", repr.name()),
+            ));
+        }
+
+        Ok(Self {
+            repr,
+            tag: tag.unwrap_or(DEFAULT_TAG_FIELD_NAME),
+            content: content.unwrap_or(DEFAULT_CONTENT_FIELD_NAME),
+        })
+    }
+}
+
+/// Returns the value of the tag identifying given item of a tagged union, i.e. its name as
+/// serialized by the Rust target. The value is a part of the layout, so every target uses it.
+pub(crate) fn tag_value(opts: &CodeGenOptions, item: &EnumItem) -> String {
+    serialized_name(opts, &item.name, opts.enum_item_name_case)
+}
+
+/// Checks that layouts of all tagged unions in the block are valid and use one of the
+/// `supported` representations. The `tagged_union_repr` option only matters for unions which
+/// do not override it, so it is not checked on its own.
+pub(crate) fn validate_tagged_unions(
+    opts: &CodeGenOptions,
+    block: &Block,
+    supported: &[TaggedUnionRepr],
+) -> Result<(), CodeGenError> {
+    for node in &block.nodes {
+        match node {
+            BlockNode::Block(block) => validate_tagged_unions(opts, block, supported)?,
+            BlockNode::Enum(e) if e.items.iter().any(|item| item.structure.is_some()) => {
+                let layout = TaggedUnionLayout::resolve(opts, e)?;
+                if !supported.contains(&layout.repr) {
+                    let message = unsupported_repr_message(layout.repr);
+                    if Annotation::find_all(&e.annotations, REPR_ANNOTATION)
+                        .next()
+                        .is_some()
+                    {
+                        return Err(invalid_annotation(e, REPR_ANNOTATION, message));
+                    }
+
+                    return Err(CodeGenError::InvalidOption(
+                        "tagged_union_repr".to_string(),
+                        format!("{}, but '{}' uses it", message, e.name),
+                    ));
+                }
+            }
+            BlockNode::Enum(e) => {
+                for name in [REPR_ANNOTATION, TAG_ANNOTATION, CONTENT_ANNOTATION] {
+                    if Annotation::find_all(&e.annotations, name).next().is_some() {
+                        return Err(invalid_annotation(
+                            e,
+                            name,
+                            "only tagged unions can be annotated with it".to_string(),
+                        ));
+                    }
+                }
+            }
+            BlockNode::Structure(_) | BlockNode::Alias(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn unsupported_repr_message(repr: TaggedUnionRepr) -> String {
+    format!(
+        "'{}' representation is not supported by this target",
+        repr.name()
+    )
+}
+
+/// Returns the only argument of an annotation with given name, if the enum is annotated
+/// with it.
+fn single_argument<'a>(e: &'a Enum, name: &str) -> Result<Option<&'a str>, CodeGenError> {
+    let mut annotations = Annotation::find_all(&e.annotations, name);
+    let Some(annotation) = annotations.next() else {
+        return Ok(None);
+    };
+
+    if annotations.next().is_some() {
+        return Err(invalid_annotation(
+            e,
+            name,
+            "used more than once".to_string(),
+        ));
+    }
+
+    match annotation.arguments.as_slice() {
+        [argument] => Ok(Some(argument)),
+        _ => Err(invalid_annotation(
+            e,
+            name,
+            "expected exactly one argument".to_string(),
+        )),
+    }
+}
+
+fn invalid_annotation(e: &Enum, annotation: &str, message: String) -> CodeGenError {
+    CodeGenError::InvalidAnnotation(e.name.clone(), annotation.to_string(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tagged_union::{tag_value, validate_tagged_unions};
+    use crate::tests::parse;
+    use crate::{
+        Case, CodeGenOptions, NameCase, RustCodeGenOptions, TaggedUnionLayout, TaggedUnionRepr,
+    };
+    use neatproto_ast::{Block, BlockNode, Enum};
+
+    fn first_enum(block: &Block) -> &Enum {
+        match &block.nodes[0] {
+            BlockNode::Enum(e) => e,
+            _ => panic!("expected an enum"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_layout() {
+        let opts = CodeGenOptions::default();
+
+        let block = parse("enum Foo { A { x: int32; }, B }");
+        assert_eq!(
+            TaggedUnionLayout::resolve(&opts, first_enum(&block)).unwrap(),
+            TaggedUnionLayout {
+                repr: TaggedUnionRepr::Adjacent,
+                tag: "kind",
+                content: "value",
+            }
+        );
+
+        let block = parse("@repr(internal) @tag(\"type\") enum Foo { A { x: int32; }, B }");
+        assert_eq!(
+            TaggedUnionLayout::resolve(&opts, first_enum(&block)).unwrap(),
+            TaggedUnionLayout {
+                repr: TaggedUnionRepr::Internal,
+                tag: "type",
+                content: "value",
+            }
+        );
+    }
+
+    #[test]
+    fn test_tag_value() {
+        let block = parse("enum Foo { FooBar { x: int32; }, Baz }");
+        let e = first_enum(&block);
+
+        let mut opts = CodeGenOptions {
+            enum_item_name_case: NameCase::Other(Case::Snake),
+            ..Default::default()
+        };
+        assert_eq!(tag_value(&opts, &e.items[0]), "foo_bar");
+
+        opts.rust = RustCodeGenOptions {
+            serde_struct_field_name_case: NameCase::Other(Case::Camel),
+            ..Default::default()
+        };
+        assert_eq!(tag_value(&opts, &e.items[0]), "fooBar");
+        assert_eq!(tag_value(&opts, &e.items[1]), "baz");
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        let opts = CodeGenOptions::default();
+        let error = |source: &str| {
+            TaggedUnionLayout::resolve(&opts, first_enum(&parse(source)))
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("@repr(\"tagged\") enum Foo { A { x: int32; } }"),
+            "Invalid annotation '@repr' of 'Foo': unknown tagged union representation 'tagged'"
+        );
+        assert_eq!(
+            error("@repr(\"internal\") @content(\"data\") enum Foo { A { x: int32; } }"),
+            "Invalid annotation '@content' of 'Foo': 'internal' tagged unions have no content field"
+        );
+        assert_eq!(
+            error("@tag(\"a\", \"b\") enum Foo { A { x: int32; } }"),
+            "Invalid annotation '@tag' of 'Foo': expected exactly one argument"
+        );
+    }
+
+    #[test]
+    fn test_validate_tagged_unions() {
+        let opts = CodeGenOptions::default();
+        let supported = [TaggedUnionRepr::Adjacent];
+
+        let block =
+            parse("enum Foo { A { x: int32; } } @repr(untagged) enum Bar { A { x: int32; } }");
+        assert_eq!(
+            validate_tagged_unions(&opts, &block, &supported)
+                .unwrap_err()
+                .to_string(),
+            "Invalid annotation '@repr' of 'Bar': 'untagged' representation is not supported by \
+             this target"
+        );
+
+        let block = parse("@tag(\"type\") enum Foo { A, B }");
+        assert!(validate_tagged_unions(&opts, &block, &supported).is_err());
+    }
+
+    #[test]
+    fn test_validate_unsupported_option() {
+        let opts = CodeGenOptions {
+            tagged_union_repr: TaggedUnionRepr::Untagged,
+            ..Default::default()
+        };
+        let supported = [TaggedUnionRepr::Adjacent];
+
+        let block = parse("struct A { x: int32; } enum B { C, D }");
+        assert!(validate_tagged_unions(&opts, &block, &supported).is_ok());
+
+        let block = parse("@repr(adjacent) enum Foo { A { x: int32; } }");
+        assert!(validate_tagged_unions(&opts, &block, &supported).is_ok());
+
+        let block =
+            parse("@repr(adjacent) enum Foo { A { x: int32; } } enum Bar { A { x: int32; } }");
+        assert_eq!(
+            validate_tagged_unions(&opts, &block, &supported)
+                .unwrap_err()
+                .to_string(),
+            "Invalid value of option 'tagged_union_repr': 'untagged' representation is not \
+             supported by this target, but 'Bar' uses it"
+        );
+    }
+}
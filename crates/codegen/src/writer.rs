@@ -16,7 +16,7 @@ impl IndentedWriter {
 
     pub fn write_indent(&mut self) {
         let spaces = self.indent * 4;
-        self.buffer.extend(std::iter::repeat(' ').take(spaces));
+        self.buffer.extend(std::iter::repeat_n(' ', spaces));
     }
 
     pub fn write(&mut self, string: &str) {
@@ -47,11 +47,22 @@ impl IndentedWriter {
         self.next_line();
     }
 
+    /// Writes every line of given string at the current indentation, which allows writing
+    /// templates spanning multiple lines. Empty lines are left without indentation.
+    pub fn write_indented_lines<S: ToString>(&mut self, string: S) {
+        for line in string.to_string().lines() {
+            if !line.is_empty() {
+                self.write_indented(line);
+            }
+            self.next_line();
+        }
+    }
+
     pub fn next_line(&mut self) {
         self.buffer.push('\n');
     }
 
-    pub fn to_string(self) -> String {
+    pub fn into_string(self) -> String {
         self.buffer
     }
 }
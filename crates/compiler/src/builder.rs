@@ -0,0 +1,381 @@
+use crate::inputs::{InputFile, collect_input_files, strip_include_dir};
+use crate::{CompilationUnit, CompileError, read_source_file, write_output_files};
+use neatproto_codegen::{
+    CodeGenOptions, NameCase, RustCodeGenOptions, TaggedUnionRepr, TargetLanguage,
+};
+use std::path::PathBuf;
+
+/// Compiles schema files into Rust code from a build script.
+///
+/// ```no_run
+/// neatproto_compiler::Builder::new()
+///     .file("proto/game.nproto")
+///     .include("proto")
+///     .rust_serde(true)
+///     .out_dir_from_env()
+///     .compile()
+///     .unwrap();
+/// ```
+///
+/// Every file is generated into its own `.rs` file in the output directory, at a path
+/// relative to the include directory containing it, so the above generates `game.rs` that
+/// can be included using `include!(concat!(env!("OUT_DIR"), "/game.rs"))`.
+pub struct Builder {
+    files: Vec<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+    options: CodeGenOptions,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            files: vec![],
+            include_dirs: vec![],
+            out_dir: None,
+            options: CodeGenOptions {
+                target_language: TargetLanguage::Rust,
+                // Generated files are usually used with `include!`, which does not allow
+                // inner attributes.
+                rust: RustCodeGenOptions {
+                    with_allow_lints: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a schema file, or a directory which is searched recursively for schema files.
+    pub fn file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    pub fn files<P: Into<PathBuf>, I: IntoIterator<Item = P>>(mut self, paths: I) -> Self {
+        self.files.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a directory that paths of generated files are made relative to, e.g.
+    /// `proto/game/player.nproto` is generated as `game/player.rs` when `proto` is included.
+    pub fn include<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    pub fn out_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.out_dir = Some(dir.into());
+        self
+    }
+
+    /// Writes generated files into the `OUT_DIR` directory provided by cargo to build scripts.
+    pub fn out_dir_from_env(mut self) -> Self {
+        self.out_dir = std::env::var_os("OUT_DIR").map(PathBuf::from);
+        self
+    }
+
+    /// Replaces all code generation options. Target language is always Rust.
+    pub fn options(mut self, options: CodeGenOptions) -> Self {
+        self.options = CodeGenOptions {
+            target_language: TargetLanguage::Rust,
+            ..options
+        };
+        self
+    }
+
+    pub fn field_name_case(mut self, case: NameCase) -> Self {
+        self.options.field_name_case = case;
+        self
+    }
+
+    pub fn type_name_case(mut self, case: NameCase) -> Self {
+        self.options.type_name_case = case;
+        self
+    }
+
+    pub fn enum_item_name_case(mut self, case: NameCase) -> Self {
+        self.options.enum_item_name_case = case;
+        self
+    }
+
+    pub fn tagged_union_repr(mut self, repr: TaggedUnionRepr) -> Self {
+        self.options.tagged_union_repr = repr;
+        self
+    }
+
+    pub fn rust_debug(mut self, enabled: bool) -> Self {
+        self.options.rust.with_debug = enabled;
+        self
+    }
+
+    pub fn rust_serde(mut self, enabled: bool) -> Self {
+        self.options.rust.with_serde = enabled;
+        self
+    }
+
+    pub fn rust_serde_field_name_case(mut self, case: NameCase) -> Self {
+        self.options.rust.serde_struct_field_name_case = case;
+        self
+    }
+
+    pub fn rust_serde_enum_repr<S: Into<String>>(mut self, repr: S) -> Self {
+        self.options.rust.serde_enum_repr = Some(repr.into());
+        self
+    }
+
+    pub fn rust_enum_display(mut self, enabled: bool) -> Self {
+        self.options.rust.with_enum_display = enabled;
+        self
+    }
+
+    /// Refers to serde traits using fully qualified paths instead of importing them.
+    pub fn rust_serde_qualified_paths(mut self, enabled: bool) -> Self {
+        self.options.rust.serde_qualified_paths = enabled;
+        self
+    }
+
+    /// Starts generated files with `#![allow(...)]`, which is disabled by default because
+    /// inner attributes can not be used in files included using `include!`.
+    pub fn rust_allow_lints(mut self, enabled: bool) -> Self {
+        self.options.rust.with_allow_lints = enabled;
+        self
+    }
+
+    /// Derives given trait for every type it is valid for, e.g. `PartialEq` or `Hash`.
+    pub fn rust_derive<S: Into<String>>(mut self, derive: S) -> Self {
+        self.options.rust.extra_derives.push(derive.into());
+        self
+    }
+
+    /// Generates a module per file along with `mod.rs` files declaring them. Since module
+    /// declarations are resolved relative to the including file, this is meant to be used
+    /// with [`Builder::out_dir`] pointing into the source tree, e.g. `src/proto`.
+    pub fn rust_module_tree(mut self, enabled: bool) -> Self {
+        self.options.rust.module_tree = enabled;
+        self
+    }
+
+    /// Generates code for all files and returns paths of written files.
+    ///
+    /// Tells cargo to rerun the build script when any of the files (or files found in the
+    /// directories) changes and reports problems as cargo diagnostics, so they are shown in
+    /// the build output.
+    pub fn compile(self) -> Result<Vec<PathBuf>, CompileError> {
+        let (result, instructions) = self.compile_with_instructions();
+        for instruction in instructions {
+            println!("cargo::{}", instruction);
+        }
+        result
+    }
+
+    /// Generates code like [`Builder::compile`], but returns instructions for cargo (without
+    /// the `cargo::` prefix) instead of printing them.
+    fn compile_with_instructions(&self) -> (Result<Vec<PathBuf>, CompileError>, Vec<String>) {
+        let mut instructions = self
+            .files
+            .iter()
+            .map(|file| format!("rerun-if-changed={}", file.display()))
+            .collect::<Vec<_>>();
+
+        let result = self.compile_files(&mut instructions);
+        if let Err(error) = &result {
+            for line in error.to_string().lines() {
+                instructions.push(format!("error={}", line));
+            }
+        }
+
+        (result, instructions)
+    }
+
+    fn compile_files(&self, instructions: &mut Vec<String>) -> Result<Vec<PathBuf>, CompileError> {
+        let out_dir = self.out_dir.as_ref().ok_or(CompileError::MissingOutDir)?;
+        let generator = self.options.target_language.generator();
+
+        let input_files = collect_input_files(&self.files)?;
+        for input_file in &input_files {
+            if !self.files.contains(&input_file.path) {
+                instructions.push(format!("rerun-if-changed={}", input_file.path.display()));
+            }
+        }
+
+        let source_files = input_files
+            .iter()
+            .map(|input_file| read_source_file(input_file.path.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let file_paths = input_files
+            .iter()
+            .map(|input_file| {
+                self.relative_output_path(input_file, instructions)
+                    .with_extension("rs")
+            })
+            .collect();
+
+        let files = CompilationUnit::new_merged(&source_files).compile_modules_with_generator(
+            generator.as_ref(),
+            &self.options,
+            file_paths,
+        )?;
+        write_output_files(out_dir, files)
+    }
+
+    /// Returns path of the file generated for given input, relative to the output directory.
+    /// Inputs outside of include directories are generated at their path relative to the
+    /// directory they were found in, or next to other files if they were given directly.
+    fn relative_output_path(
+        &self,
+        input_file: &InputFile,
+        instructions: &mut Vec<String>,
+    ) -> PathBuf {
+        let include_dirs = self.include_dirs.iter().map(PathBuf::as_path);
+        strip_include_dir(&input_file.path, include_dirs).unwrap_or_else(|| {
+            if !self.include_dirs.is_empty() {
+                instructions.push(format!(
+                    "warning='{}' is not inside any include directory, generating it as '{}'",
+                    input_file.path.display(),
+                    input_file.relative_path.with_extension("rs").display()
+                ));
+            }
+            input_file.relative_path.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Builder, CompileError};
+    use std::path::Path;
+
+    fn write_file(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_relative_output_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let proto_dir = dir.path().join("proto");
+        let out_dir = dir.path().join("out");
+        write_file(
+            &proto_dir.join("game/player.nproto"),
+            "struct Player { id: uint64; }",
+        );
+        write_file(
+            &dir.path().join("item.nproto"),
+            "struct Item { id: uint64; }",
+        );
+
+        let (result, instructions) = Builder::new()
+            .file(proto_dir.join("game/player.nproto"))
+            .file(dir.path().join("item.nproto"))
+            .include(&proto_dir)
+            .out_dir(&out_dir)
+            .compile_with_instructions();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![out_dir.join("game/player.rs"), out_dir.join("item.rs")]
+        );
+        assert!(instructions.contains(&format!(
+            "warning='{}' is not inside any include directory, generating it as 'item.rs'",
+            dir.path().join("item.nproto").display()
+        )));
+    }
+
+    #[test]
+    fn test_rerun_if_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let proto_dir = dir.path().join("proto");
+        write_file(&proto_dir.join("a.nproto"), "struct A { x: bool; }");
+        write_file(&proto_dir.join("game/b.nproto"), "struct B { a: A; }");
+
+        let (result, instructions) = Builder::new()
+            .file(&proto_dir)
+            .out_dir(dir.path().join("out"))
+            .compile_with_instructions();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                dir.path().join("out/a.rs"),
+                dir.path().join("out/game/b.rs")
+            ]
+        );
+        assert_eq!(
+            instructions,
+            vec![
+                format!("rerun-if-changed={}", proto_dir.display()),
+                format!("rerun-if-changed={}", proto_dir.join("a.nproto").display()),
+                format!(
+                    "rerun-if-changed={}",
+                    proto_dir.join("game/b.nproto").display()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.nproto");
+        write_file(&file, "struct A { x: bool }");
+
+        let (result, instructions) = Builder::new()
+            .file(&file)
+            .out_dir(dir.path())
+            .compile_with_instructions();
+
+        let error = result.unwrap_err();
+        assert!(matches!(error, CompileError::Parse(_)));
+        assert_eq!(
+            instructions[0],
+            format!("rerun-if-changed={}", file.display())
+        );
+        assert_eq!(
+            instructions[1..],
+            error
+                .to_string()
+                .lines()
+                .map(|line| format!("error={}", line))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_missing_out_dir() {
+        let (result, instructions) = Builder::new().file("a.nproto").compile_with_instructions();
+
+        assert!(matches!(result, Err(CompileError::MissingOutDir)));
+        assert_eq!(
+            instructions,
+            vec![
+                "rerun-if-changed=a.nproto".to_string(),
+                format!("error={}", CompileError::MissingOutDir),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("a.nproto"), "struct A { x: bool; }");
+
+        let (result, _) = Builder::new()
+            .file(dir.path().join("a.nproto"))
+            .out_dir(dir.path())
+            .compile_with_instructions();
+
+        assert_eq!(result.unwrap(), vec![dir.path().join("a.rs")]);
+        assert!(
+            std::fs::read_to_string(dir.path().join("a.rs"))
+                .unwrap()
+                .contains("pub struct A {")
+        );
+    }
+}
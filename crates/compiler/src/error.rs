@@ -1,15 +1,67 @@
+use crate::plugin::PluginError;
+use neatproto_analysis::AnalysisError;
+use neatproto_codegen::CodeGenError;
 use neatproto_parser::LocalizedParseError;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum CompileError {
+    /// Reading or writing of the file at given path has failed.
+    Io(PathBuf, std::io::Error),
     Parse(LocalizedParseError),
+    Analysis(AnalysisError),
+    Codegen(CodeGenError),
+    UnknownTarget(String),
+    Plugin(PluginError),
+    Config(String),
+    /// Two source files at given paths would be generated into the same output file.
+    DuplicateOutput(PathBuf, String, String),
+    /// Output directory was not given and could not be read from the `OUT_DIR` variable.
+    MissingOutDir,
 }
 
 impl Display for CompileError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            CompileError::Io(path, error) => {
+                write!(f, "Failed to access '{}': {}", path.display(), error)
+            }
             CompileError::Parse(parse_error) => write!(f, "Parse error: {}", parse_error),
+            CompileError::Analysis(analysis_error) => {
+                write!(f, "Analysis error: {}", analysis_error)
+            }
+            CompileError::Codegen(codegen_error) => write!(f, "{}", codegen_error),
+            CompileError::UnknownTarget(target) => write!(f, "Unknown target '{}'", target),
+            CompileError::Plugin(plugin_error) => write!(f, "{}", plugin_error),
+            CompileError::Config(message) => write!(f, "Invalid project config: {}", message),
+            CompileError::DuplicateOutput(output, first, second) => write!(
+                f,
+                "Both '{}' and '{}' would be generated into '{}'",
+                first,
+                second,
+                output.display()
+            ),
+            CompileError::MissingOutDir => write!(
+                f,
+                "Output directory was not set and OUT_DIR environment variable is not available"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompileError::Io(_, error) => Some(error),
+            CompileError::Parse(error) => Some(error),
+            CompileError::Analysis(error) => Some(error),
+            CompileError::Codegen(error) => Some(error),
+            CompileError::Plugin(error) => Some(error),
+            CompileError::UnknownTarget(_)
+            | CompileError::Config(_)
+            | CompileError::DuplicateOutput(..)
+            | CompileError::MissingOutDir => None,
         }
     }
 }
@@ -0,0 +1,73 @@
+use crate::CompileError;
+use std::path::{Path, PathBuf};
+
+pub const SOURCE_FILE_EXTENSION: &str = "nproto";
+
+#[derive(Debug)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Path of the file relative to the directory it was found in, or just the file name
+    /// if it was given directly.
+    pub relative_path: PathBuf,
+}
+
+/// Expands given paths into a list of input files. Directories are searched recursively
+/// for files with the `.nproto` extension.
+pub fn collect_input_files(paths: &[PathBuf]) -> Result<Vec<InputFile>, CompileError> {
+    let mut files = vec![];
+
+    for path in paths {
+        if path.is_dir() {
+            let mut found = vec![];
+            find_source_files(path, &mut found)?;
+            found.sort();
+
+            files.extend(found.into_iter().map(|found_path| {
+                InputFile {
+                    relative_path: found_path
+                        .strip_prefix(path)
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|_| found_path.clone()),
+                    path: found_path,
+                }
+            }));
+        } else {
+            files.push(InputFile {
+                relative_path: path
+                    .file_name()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| path.clone()),
+                path: path.clone(),
+            });
+        }
+    }
+
+    Ok(files)
+}
+
+/// Returns path of given file relative to the first of `include_dirs` that contains it.
+pub fn strip_include_dir<'a, I: IntoIterator<Item = &'a Path>>(
+    path: &Path,
+    include_dirs: I,
+) -> Option<PathBuf> {
+    include_dirs
+        .into_iter()
+        .find_map(|include_dir| path.strip_prefix(include_dir).ok())
+        .map(Path::to_path_buf)
+}
+
+fn find_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), CompileError> {
+    let io_error = |error| CompileError::Io(dir.to_path_buf(), error);
+    for entry in std::fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            find_source_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == SOURCE_FILE_EXTENSION)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -1,18 +1,23 @@
+mod builder;
 pub mod error;
+pub mod inputs;
+pub mod plugin;
+pub mod project;
 pub mod unit;
 
+pub use builder::Builder;
+pub use error::*;
 use neatproto_codegen::CodeGenOptions;
 pub use neatproto_parser::SourceFile;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 pub use unit::*;
-pub use error::*;
 
 pub fn compile_nproto_to_string(
     input_file_path: PathBuf,
     code_gen_opts: &CodeGenOptions,
 ) -> Result<String, CompileError> {
-    let source_file =
-        SourceFile::new_from_path(input_file_path).expect("failed to read input file");
+    let source_file = read_source_file(input_file_path)?;
     let unit = CompilationUnit::new(&source_file);
     unit.compile(code_gen_opts)
 }
@@ -23,15 +28,44 @@ pub fn compile_nproto_to_file(
     code_gen_opts: &CodeGenOptions,
 ) -> Result<(), CompileError> {
     let result = compile_nproto_to_string(input_file_path, code_gen_opts)?;
+    write_output_file(output_file_path, result)
+}
+
+pub fn read_source_file(path: PathBuf) -> Result<SourceFile, CompileError> {
+    SourceFile::new_from_path(path.clone()).map_err(|error| CompileError::Io(path, error))
+}
+
+/// Writes generated code to given path, creating parent directories if necessary.
+pub fn write_output_file(output_file_path: PathBuf, contents: String) -> Result<(), CompileError> {
+    if let Some(output_file_parent_dir) = output_file_path.parent() {
+        std::fs::create_dir_all(output_file_parent_dir)
+            .map_err(|error| CompileError::Io(output_file_parent_dir.to_path_buf(), error))?;
+    }
+    std::fs::write(&output_file_path, contents)
+        .map_err(|error| CompileError::Io(output_file_path, error))
+}
 
-    let output_file_path_buf = output_file_path.to_path_buf();
-    let output_file_parent_dir = output_file_path_buf
-        .parent()
-        .expect("output file's parent directory is invalid");
-    std::fs::create_dir_all(output_file_parent_dir)
-        .expect("failed to create output file parent directory");
-    std::fs::write(output_file_path_buf, result)
-        .expect("failed to write generated code to output file");
+/// Writes generated files into given directory and returns their paths.
+pub fn write_output_files(
+    output_dir: &Path,
+    files: BTreeMap<PathBuf, String>,
+) -> Result<Vec<PathBuf>, CompileError> {
+    let mut written_files = vec![];
+    for (file_path, contents) in files {
+        let output_file_path = output_dir.join(file_path);
+        write_output_file(output_file_path.clone(), contents)?;
+        written_files.push(output_file_path);
+    }
+    Ok(written_files)
+}
 
-    Ok(())
+/// Splits path of an output file into its directory and file name, so that generators which
+/// split their output place additional files next to it.
+pub fn split_output_file_path(output_file_path: &Path) -> (&Path, &Path) {
+    let output_dir = output_file_path.parent().unwrap_or(Path::new(""));
+    let file_name = output_file_path
+        .file_name()
+        .map(Path::new)
+        .unwrap_or(output_file_path);
+    (output_dir, file_name)
 }
@@ -0,0 +1,308 @@
+use neatproto_ast::{Annotation, Block, BlockNode, BuiltinTypeName, StructureField, TypeName};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::{Component, Path};
+use std::process::{Command, ExitStatus, Stdio};
+
+pub const PLUGIN_EXECUTABLE_PREFIX: &str = "neatproto-gen-";
+
+#[derive(Debug, Serialize)]
+pub struct PluginRequest {
+    /// Free-form parameter passed to the plugin on the command line.
+    pub parameter: Option<String>,
+    pub files: Vec<SchemaFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaFile {
+    pub path: String,
+    pub nodes: Vec<SchemaNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaNode {
+    Structure {
+        name: String,
+        fields: Vec<SchemaField>,
+        annotations: Vec<SchemaAnnotation>,
+    },
+    Enum {
+        name: String,
+        items: Vec<SchemaEnumItem>,
+        annotations: Vec<SchemaAnnotation>,
+    },
+    Alias {
+        name: String,
+        aliased_type: SchemaType,
+        annotations: Vec<SchemaAnnotation>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaAnnotation {
+    pub name: String,
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: SchemaType,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaEnumItem {
+    pub name: String,
+    pub value: Option<String>,
+    /// Fields of the item, present only for tagged union cases.
+    pub fields: Option<Vec<SchemaField>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaType {
+    pub name: String,
+    pub is_builtin: bool,
+    pub is_array: bool,
+    pub array_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PluginResponse {
+    #[serde(default)]
+    pub files: Vec<GeneratedFile>,
+    /// Error reported by the plugin, e.g. when the schema uses something it does not support.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeneratedFile {
+    /// Path of the file, relative to the output directory.
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub enum PluginError {
+    NotFound(String),
+    Io(String, std::io::Error),
+    Failed(String, ExitStatus),
+    InvalidResponse(String, serde_json::Error),
+    Reported(String, String),
+    /// Name of a generated file which is not a relative path inside the output directory.
+    InvalidFileName(String, String),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::NotFound(name) => write!(
+                f,
+                "Plugin '{}' was not found, make sure '{}{}' is available on PATH",
+                name, PLUGIN_EXECUTABLE_PREFIX, name
+            ),
+            PluginError::Io(name, error) => {
+                write!(f, "Failed to communicate with plugin '{}': {}", name, error)
+            }
+            PluginError::Failed(name, status) => {
+                write!(f, "Plugin '{}' exited with {}", name, status)
+            }
+            PluginError::InvalidResponse(name, error) => {
+                write!(f, "Plugin '{}' returned invalid response: {}", name, error)
+            }
+            PluginError::Reported(name, error) => write!(f, "Plugin '{}': {}", name, error),
+            PluginError::InvalidFileName(name, file_name) => write!(
+                f,
+                "Plugin '{}' generated file '{}' outside of the output directory",
+                name, file_name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PluginError::Io(_, error) => Some(error),
+            PluginError::InvalidResponse(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl SchemaFile {
+    pub fn new(path: String, root_block: &Block) -> Self {
+        let mut nodes = vec![];
+        collect_nodes(root_block, &mut nodes);
+        Self { path, nodes }
+    }
+}
+
+fn collect_nodes(block: &Block, nodes: &mut Vec<SchemaNode>) {
+    for node in &block.nodes {
+        match node {
+            BlockNode::Block(block) => collect_nodes(block, nodes),
+            BlockNode::Structure(structure) => nodes.push(SchemaNode::Structure {
+                name: structure.name.clone(),
+                fields: convert_fields(&structure.fields),
+                annotations: convert_annotations(&structure.annotations),
+            }),
+            BlockNode::Enum(e) => nodes.push(SchemaNode::Enum {
+                name: e.name.clone(),
+                items: e
+                    .items
+                    .iter()
+                    .map(|item| SchemaEnumItem {
+                        name: item.name.clone(),
+                        value: item.value_token.as_ref().map(|token| token.value()),
+                        fields: item
+                            .structure
+                            .as_ref()
+                            .map(|structure| convert_fields(&structure.fields)),
+                    })
+                    .collect(),
+                annotations: convert_annotations(&e.annotations),
+            }),
+            BlockNode::Alias(alias) => nodes.push(SchemaNode::Alias {
+                name: alias.alias_name.clone(),
+                aliased_type: SchemaType {
+                    name: alias.aliased_type_name.clone(),
+                    is_builtin: BuiltinTypeName::parse(&alias.aliased_type_name).is_some(),
+                    is_array: false,
+                    array_size: None,
+                },
+                annotations: convert_annotations(&alias.annotations),
+            }),
+        }
+    }
+}
+
+fn convert_annotations(annotations: &[Annotation]) -> Vec<SchemaAnnotation> {
+    annotations
+        .iter()
+        .map(|annotation| SchemaAnnotation {
+            name: annotation.name.clone(),
+            arguments: annotation.arguments.clone(),
+        })
+        .collect()
+}
+
+fn convert_fields(fields: &[StructureField]) -> Vec<SchemaField> {
+    fields
+        .iter()
+        .map(|field| SchemaField {
+            name: field.name.clone(),
+            type_name: convert_type_name(&field.type_name),
+        })
+        .collect()
+}
+
+fn convert_type_name(type_name: &TypeName) -> SchemaType {
+    let name = type_name
+        .resolved
+        .clone()
+        .unwrap_or(type_name.token.value());
+    SchemaType {
+        is_builtin: BuiltinTypeName::parse(&name).is_some(),
+        name,
+        is_array: type_name.is_array,
+        array_size: type_name
+            .array_size
+            .as_ref()
+            .and_then(|size| size.value().parse().ok()),
+    }
+}
+
+/// Runs plugin with given name and returns the files it generated.
+///
+/// A plugin is an executable named `neatproto-gen-<name>` available on `PATH`. It receives
+/// the request as JSON on its standard input and is expected to write a [`PluginResponse`]
+/// as JSON to its standard output.
+pub fn run_plugin(name: &str, request: &PluginRequest) -> Result<Vec<GeneratedFile>, PluginError> {
+    let mut child = Command::new(format!("{}{}", PLUGIN_EXECUTABLE_PREFIX, name))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => PluginError::NotFound(name.to_string()),
+            _ => PluginError::Io(name.to_string(), error),
+        })?;
+
+    // Write the request from a separate thread, so that a plugin which starts writing its
+    // response before reading the whole request can not deadlock us.
+    let request = serde_json::to_vec(request).expect("failed to serialize plugin request");
+    let mut stdin = child.stdin.take().expect("plugin stdin is not piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&request));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| PluginError::Io(name.to_string(), error))?;
+
+    let write_result = writer.join().expect("plugin request writer panicked");
+
+    if !output.status.success() {
+        return Err(PluginError::Failed(name.to_string(), output.status));
+    }
+
+    write_result.map_err(|error| PluginError::Io(name.to_string(), error))?;
+
+    let response: PluginResponse = serde_json::from_slice(&output.stdout)
+        .map_err(|error| PluginError::InvalidResponse(name.to_string(), error))?;
+
+    match response.error {
+        Some(error) => Err(PluginError::Reported(name.to_string(), error)),
+        None => validate_generated_files(name, response.files),
+    }
+}
+
+/// Makes sure that every generated file is written inside the output directory, i.e. that
+/// its name is a relative path without `..` components.
+fn validate_generated_files(
+    name: &str,
+    files: Vec<GeneratedFile>,
+) -> Result<Vec<GeneratedFile>, PluginError> {
+    for file in &files {
+        let is_valid = !file.name.is_empty()
+            && Path::new(&file.name)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+        if !is_valid {
+            return Err(PluginError::InvalidFileName(
+                name.to_string(),
+                file.name.clone(),
+            ));
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugin::{GeneratedFile, PluginError, validate_generated_files};
+
+    fn validate(file_name: &str) -> Result<Vec<GeneratedFile>, PluginError> {
+        let file = GeneratedFile {
+            name: file_name.to_string(),
+            content: String::new(),
+        };
+        validate_generated_files("test", vec![file])
+    }
+
+    #[test]
+    fn test_validate_generated_files() {
+        assert!(validate("types.ts").is_ok());
+        assert!(validate("./game/player.ts").is_ok());
+
+        for file_name in ["", "../types.ts", "game/../../types.ts", "/etc/types.ts"] {
+            assert!(matches!(
+                validate(file_name),
+                Err(PluginError::InvalidFileName(_, name)) if name == file_name
+            ));
+        }
+    }
+}
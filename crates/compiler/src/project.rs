@@ -0,0 +1,269 @@
+use crate::inputs::strip_include_dir;
+use crate::{
+    CompilationUnit, CompileError, read_source_file, split_output_file_path, write_output_files,
+};
+use neatproto_codegen::{CodeGenOptions, CodeGenRegistry, TargetLanguage};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_CONFIG_FILE_NAME: &str = "neatproto.toml";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Glob patterns of input files, relative to the directory containing the config file.
+    pub inputs: Vec<String>,
+
+    /// Directories that input paths are made relative to when outputs are written into
+    /// a directory, e.g. `proto/game/player.nproto` is generated as `game/player.rs` when
+    /// `proto` is included.
+    #[serde(default)]
+    pub include: Vec<PathBuf>,
+
+    #[serde(default, rename = "target")]
+    pub targets: Vec<TargetConfig>,
+}
+
+#[derive(Debug)]
+pub struct TargetConfig {
+    /// Name of the code generator, e.g. `rust` or `csharp`.
+    pub language: String,
+
+    /// File that all inputs are merged into, or a directory (when the path ends with
+    /// a separator or already exists as a directory) that receives one file per input.
+    pub output: PathBuf,
+
+    /// Code generation options, given next to `language` and `output`.
+    pub options: CodeGenOptions,
+}
+
+// Implemented manually instead of using `#[serde(flatten)]`, which makes serde accept any
+// unknown key, so that misspelled options are reported instead of being silently ignored.
+impl<'de> Deserialize<'de> for TargetConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut table = toml::Table::deserialize(deserializer)?;
+
+        let mut take_field = |name: &'static str| {
+            table
+                .remove(name)
+                .ok_or_else(|| serde::de::Error::missing_field(name))
+        };
+        let language = take_field("language")?
+            .try_into()
+            .map_err(serde::de::Error::custom)?;
+        let output = take_field("output")?
+            .try_into()
+            .map_err(serde::de::Error::custom)?;
+
+        let options = toml::Value::Table(table)
+            .try_into()
+            .map_err(serde::de::Error::custom)?;
+
+        Ok(Self {
+            language,
+            output,
+            options,
+        })
+    }
+}
+
+pub struct Project {
+    pub root_dir: PathBuf,
+    pub config: ProjectConfig,
+}
+
+impl Project {
+    pub fn load(config_path: PathBuf) -> Result<Self, CompileError> {
+        let contents = std::fs::read_to_string(&config_path)
+            .map_err(|error| CompileError::Io(config_path.clone(), error))?;
+        let mut config: ProjectConfig = toml::from_str(&contents).map_err(|error| {
+            CompileError::Config(format!("{}: {}", config_path.display(), error))
+        })?;
+
+        for target in &mut config.targets {
+            // Languages which are not built-in may still be provided by the registry
+            // passed to `build`, which reports the ones that are not.
+            if let Some(target_language) = TargetLanguage::from_name(&target.language) {
+                target.options.target_language = target_language;
+            }
+        }
+
+        let root_dir = config_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        Ok(Self { root_dir, config })
+    }
+
+    /// Returns paths of all files matching input patterns, in a stable order.
+    pub fn input_files(&self) -> Result<Vec<PathBuf>, CompileError> {
+        let mut files = vec![];
+
+        for pattern in &self.config.inputs {
+            let full_pattern = self.root_dir.join(pattern);
+            let paths = glob::glob(&full_pattern.to_string_lossy()).map_err(|error| {
+                CompileError::Config(format!("invalid input pattern '{}': {}", pattern, error))
+            })?;
+
+            for path in paths.flatten() {
+                if path.is_file() {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Generates code for every target and returns paths of written files.
+    pub fn build(&self, registry: &CodeGenRegistry) -> Result<Vec<PathBuf>, CompileError> {
+        let source_files = self
+            .input_files()?
+            .into_iter()
+            .map(read_source_file)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut written_files = vec![];
+
+        for target in &self.config.targets {
+            let generator = registry.get(&target.language).ok_or_else(|| {
+                CompileError::Config(format!(
+                    "unknown language '{}' of target '{}', expected one of: {}",
+                    target.language,
+                    target.output.display(),
+                    registry.names().collect::<Vec<_>>().join(", ")
+                ))
+            })?;
+            let output = self.root_dir.join(&target.output);
+
+            if is_directory_path(&target.output) || output.is_dir() {
+                let file_paths = source_files
+                    .iter()
+                    .map(|source_file| {
+                        self.relative_input_path(Path::new(&source_file.path))
+                            .with_extension(generator.file_extension())
+                    })
+                    .collect();
+                let files = CompilationUnit::new_merged(&source_files)
+                    .compile_modules_with_generator(generator, &target.options, file_paths)?;
+                written_files.extend(write_output_files(&output, files)?);
+            } else {
+                let (output_dir, file_name) = split_output_file_path(&output);
+                let files = CompilationUnit::new_merged(&source_files)
+                    .compile_files_with_generator(generator, &target.options, file_name)?;
+                written_files.extend(write_output_files(output_dir, files)?);
+            }
+        }
+
+        Ok(written_files)
+    }
+
+    fn relative_input_path(&self, path: &Path) -> PathBuf {
+        let include_dirs = self
+            .config
+            .include
+            .iter()
+            .map(|include| self.root_dir.join(include))
+            .chain(std::iter::once(self.root_dir.clone()))
+            .collect::<Vec<_>>();
+
+        strip_include_dir(path, include_dirs.iter().map(PathBuf::as_path))
+            .unwrap_or_else(|| path.file_name().map(PathBuf::from).unwrap_or_default())
+    }
+}
+
+fn is_directory_path(path: &Path) -> bool {
+    path.as_os_str()
+        .to_string_lossy()
+        .ends_with(std::path::is_separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CompileError;
+    use crate::project::{Project, ProjectConfig};
+    use neatproto_codegen::{Case, CodeGenRegistry, NameCase};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_project_config() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            inputs = ["proto/**/*.nproto"]
+            include = ["proto"]
+
+            [[target]]
+            language = "rust"
+            output = "server/src/proto.rs"
+            field_name_case = "snake"
+
+            [target.rust]
+            with_serde = true
+
+            [[target]]
+            language = "csharp"
+            output = "client/Proto/"
+
+            [target.csharp]
+            namespace = "Game.Proto"
+            "#,
+        )
+        .expect("failed to parse config");
+
+        assert_eq!(config.inputs, vec!["proto/**/*.nproto"]);
+        assert_eq!(config.targets.len(), 2);
+
+        let rust = &config.targets[0];
+        assert_eq!(rust.language, "rust");
+        assert!(rust.options.rust.with_serde);
+        assert!(matches!(
+            rust.options.field_name_case,
+            NameCase::Other(Case::Snake)
+        ));
+
+        let csharp = &config.targets[1];
+        assert_eq!(csharp.options.csharp.namespace, "Game.Proto");
+        assert!(csharp.options.csharp.with_json_convert_for_union_tags);
+    }
+
+    #[test]
+    fn test_project_config_unknown_options() {
+        for options in [
+            "field_name_cas = \"snake\"",
+            "[target.rust]\nwith_serd = true",
+        ] {
+            let error = toml::from_str::<ProjectConfig>(&format!(
+                "inputs = []\n[[target]]\nlanguage = \"rust\"\noutput = \"proto.rs\"\n{}",
+                options
+            ))
+            .expect_err("unknown option was accepted");
+            assert!(error.to_string().contains("unknown field"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_project_unknown_language() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            inputs = []
+
+            [[target]]
+            language = "rsut"
+            output = "proto.rs"
+            "#,
+        )
+        .expect("failed to parse config");
+
+        let project = Project {
+            root_dir: PathBuf::new(),
+            config,
+        };
+        let error = project
+            .build(&CodeGenRegistry::default())
+            .expect_err("unknown language was accepted");
+        assert!(matches!(error, CompileError::Config(message) if message.contains("'rsut'")));
+    }
+}
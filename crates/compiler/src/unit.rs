@@ -1,24 +1,331 @@
 use crate::CompileError;
-use neatproto_analysis::analyze_block;
-use neatproto_codegen::{CodeGenOptions, generate_code};
+use crate::plugin::{GeneratedFile, PluginRequest, SchemaFile, run_plugin};
+use neatproto_analysis::{analyze_block, analyze_blocks};
+use neatproto_ast::{Block, BlockNode, Structure};
+use neatproto_codegen::{
+    CodeGenOptions, CodeGenRegistry, CodeGenerator, validate_annotation_names,
+};
 use neatproto_parser::{SourceFile, parse_block};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 pub struct CompilationUnit<'a> {
-    root_source_file: &'a SourceFile,
+    source_files: Vec<&'a SourceFile>,
 }
 
 impl<'a> CompilationUnit<'a> {
     pub fn new(root_source_file: &'a SourceFile) -> Self {
-        Self { root_source_file }
+        Self {
+            source_files: vec![root_source_file],
+        }
+    }
+
+    /// Creates a unit from multiple source files, whose declarations are merged together
+    /// and generated into a single output.
+    pub fn new_merged<I: IntoIterator<Item = &'a SourceFile>>(source_files: I) -> Self {
+        Self {
+            source_files: source_files.into_iter().collect(),
+        }
+    }
+
+    /// Parses, analyzes and validates the unit without generating any code. Files are
+    /// analyzed together, like when compiling them. Without a generator, only annotations
+    /// known to every built-in target are accepted.
+    pub fn check(
+        self,
+        generator: Option<&dyn CodeGenerator>,
+        codegen_opts: &CodeGenOptions,
+    ) -> Result<(), CompileError> {
+        let mut blocks = self.parse()?;
+        analyze_blocks(&mut blocks).map_err(CompileError::Analysis)?;
+
+        for block in &blocks {
+            match generator {
+                Some(generator) => generator.validate(codegen_opts, block),
+                None => validate_annotation_names(block, &[]),
+            }
+            .map_err(CompileError::Codegen)?;
+        }
+        Ok(())
     }
 
     pub fn compile(self, codegen_opts: &CodeGenOptions) -> Result<String, CompileError> {
-        match parse_block(&mut self.root_source_file.tokens()) {
-            Ok(mut root_block) => {
-                analyze_block(&mut root_block);
-                Ok(generate_code(codegen_opts, &root_block))
+        let generator = codegen_opts.target_language.generator();
+        self.compile_with_generator(generator.as_ref(), codegen_opts)
+    }
+
+    /// Compiles the unit using a generator registered under given name, which allows using
+    /// custom backends alongside the built-in ones.
+    pub fn compile_with_registry(
+        self,
+        registry: &CodeGenRegistry,
+        target: &str,
+        codegen_opts: &CodeGenOptions,
+    ) -> Result<String, CompileError> {
+        let generator = registry
+            .get(target)
+            .ok_or_else(|| CompileError::UnknownTarget(target.to_string()))?;
+        self.compile_with_generator(generator, codegen_opts)
+    }
+
+    pub fn compile_with_generator(
+        self,
+        generator: &dyn CodeGenerator,
+        codegen_opts: &CodeGenOptions,
+    ) -> Result<String, CompileError> {
+        let root_block = self.analyze()?;
+        generator
+            .generate(codegen_opts, &root_block)
+            .map_err(CompileError::Codegen)
+    }
+
+    /// Compiles the unit into files keyed by their paths relative to the output directory.
+    /// Generators which do not split their output return a single file at `file_path`.
+    pub fn compile_files_with_generator(
+        self,
+        generator: &dyn CodeGenerator,
+        codegen_opts: &CodeGenOptions,
+        file_path: &Path,
+    ) -> Result<BTreeMap<PathBuf, String>, CompileError> {
+        let root_block = self.analyze()?;
+        generator
+            .generate_files(codegen_opts, &root_block, file_path)
+            .map_err(CompileError::Codegen)
+    }
+
+    /// Compiles the unit using an external plugin executable.
+    /// See [`run_plugin`] for details about how plugins are invoked.
+    pub fn compile_with_plugin(
+        self,
+        plugin_name: &str,
+        parameter: Option<String>,
+    ) -> Result<Vec<GeneratedFile>, CompileError> {
+        let mut blocks = self.parse()?;
+        analyze_blocks(&mut blocks).map_err(CompileError::Analysis)?;
+
+        let files = self
+            .source_files
+            .iter()
+            .zip(&blocks)
+            .map(|(source_file, block)| SchemaFile::new(source_file.path.clone(), block))
+            .collect();
+
+        let request = PluginRequest { parameter, files };
+        run_plugin(plugin_name, &request).map_err(CompileError::Plugin)
+    }
+
+    /// Compiles every source file of the unit into its own output, which allows generators
+    /// to refer to types declared in other files. `file_paths` contains the path that code
+    /// of each source file would be written to, relative to the output directory.
+    pub fn compile_modules_with_generator(
+        self,
+        generator: &dyn CodeGenerator,
+        codegen_opts: &CodeGenOptions,
+        file_paths: Vec<PathBuf>,
+    ) -> Result<BTreeMap<PathBuf, String>, CompileError> {
+        let mut sources = BTreeMap::new();
+        for (file_path, source_file) in file_paths.iter().zip(&self.source_files) {
+            if let Some(other_path) = sources.insert(file_path, &source_file.path) {
+                return Err(CompileError::DuplicateOutput(
+                    file_path.clone(),
+                    other_path.clone(),
+                    source_file.path.clone(),
+                ));
             }
-            Err(error) => Err(CompileError::Parse(error)),
         }
+
+        let mut blocks = self.parse()?;
+        analyze_blocks(&mut blocks).map_err(CompileError::Analysis)?;
+
+        let modules = file_paths.into_iter().zip(blocks).collect::<Vec<_>>();
+        generator
+            .generate_modules(codegen_opts, &modules)
+            .map_err(CompileError::Codegen)
+    }
+
+    /// Parses all files into a single analyzed block, ready to be passed to a generator.
+    fn analyze(self) -> Result<Block, CompileError> {
+        let mut root_block = Block { nodes: vec![] };
+        for block in self.parse()? {
+            root_block.nodes.extend(block.nodes);
+        }
+
+        analyze_block(&mut root_block).map_err(CompileError::Analysis)?;
+        Ok(root_block)
+    }
+
+    /// Returns paths of source files affected by changes of files for which `is_changed`
+    /// returns true, i.e. the changed files themselves along with files that refer to types
+    /// declared in any affected file, since their generated code may depend on those types.
+    pub fn affected_source_files<F: Fn(&SourceFile) -> bool>(
+        &self,
+        is_changed: F,
+    ) -> Result<BTreeSet<String>, CompileError> {
+        let blocks = self.parse()?;
+        let type_names = blocks
+            .iter()
+            .map(|block| {
+                let mut declared = BTreeSet::new();
+                let mut referenced = BTreeSet::new();
+                collect_type_names(block, &mut declared, &mut referenced);
+                (declared, referenced)
+            })
+            .collect::<Vec<_>>();
+
+        let mut affected = self
+            .source_files
+            .iter()
+            .map(|source_file| is_changed(source_file))
+            .collect::<Vec<_>>();
+
+        loop {
+            let affected_types = type_names
+                .iter()
+                .zip(&affected)
+                .filter(|(_, is_affected)| **is_affected)
+                .flat_map(|((declared, _), _)| declared)
+                .collect::<BTreeSet<_>>();
+
+            let mut found_new = false;
+            for ((_, referenced), is_affected) in type_names.iter().zip(&mut affected) {
+                if !*is_affected && referenced.iter().any(|name| affected_types.contains(name)) {
+                    *is_affected = true;
+                    found_new = true;
+                }
+            }
+
+            if !found_new {
+                break;
+            }
+        }
+
+        Ok(self
+            .source_files
+            .iter()
+            .zip(affected)
+            .filter(|(_, is_affected)| *is_affected)
+            .map(|(source_file, _)| source_file.path.clone())
+            .collect())
+    }
+
+    fn parse(&self) -> Result<Vec<Block>, CompileError> {
+        self.source_files
+            .iter()
+            .map(|source_file| parse_block(&mut source_file.tokens()).map_err(CompileError::Parse))
+            .collect()
+    }
+}
+
+/// Collects names of types declared in given block and names of types its declarations
+/// refer to.
+fn collect_type_names(
+    block: &Block,
+    declared: &mut BTreeSet<String>,
+    referenced: &mut BTreeSet<String>,
+) {
+    for node in &block.nodes {
+        match node {
+            BlockNode::Block(block) => collect_type_names(block, declared, referenced),
+            BlockNode::Structure(structure) => {
+                declared.insert(structure.name.clone());
+                collect_field_type_names(structure, referenced);
+            }
+            BlockNode::Enum(e) => {
+                declared.insert(e.name.clone());
+                for structure in e.items.iter().filter_map(|item| item.structure.as_ref()) {
+                    collect_field_type_names(structure, referenced);
+                }
+            }
+            BlockNode::Alias(alias) => {
+                declared.insert(alias.alias_name.clone());
+                referenced.insert(alias.aliased_type_name.clone());
+            }
+        }
+    }
+}
+
+fn collect_field_type_names(structure: &Structure, referenced: &mut BTreeSet<String>) {
+    referenced.extend(
+        structure
+            .fields
+            .iter()
+            .map(|field| field.type_name.token.value()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugin::PluginError;
+    use crate::{CompilationUnit, CompileError};
+    use neatproto_codegen::{CodeGenOptions, RustCodeGenerator};
+    use neatproto_parser::SourceFile;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_duplicate_module_paths() {
+        let source_files = [
+            SourceFile::new_from_source("a/x.nproto", "struct A { x: bool; }"),
+            SourceFile::new_from_source("b/x.nproto", "struct B { x: bool; }"),
+        ];
+        let error = CompilationUnit::new_merged(&source_files)
+            .compile_modules_with_generator(
+                &RustCodeGenerator,
+                &CodeGenOptions::default(),
+                vec![PathBuf::from("x.rs"), PathBuf::from("x.rs")],
+            )
+            .expect_err("duplicate output path was accepted");
+        assert!(matches!(
+            error,
+            CompileError::DuplicateOutput(output, first, second)
+                if output.as_path() == Path::new("x.rs") && first == "a/x.nproto" && second == "b/x.nproto"
+        ));
+    }
+
+    #[test]
+    fn test_plugin_analysis() {
+        let source_files = [
+            SourceFile::new_from_source("a.nproto", "struct A { x: bool; }"),
+            SourceFile::new_from_source("b.nproto", "struct A { y: bool; }"),
+        ];
+        assert!(matches!(
+            CompilationUnit::new_merged(&source_files).compile_with_plugin("missing", None),
+            Err(CompileError::Analysis(_))
+        ));
+
+        let source_files = [
+            SourceFile::new_from_source("a.nproto", "struct A { x: bool; }"),
+            SourceFile::new_from_source("b.nproto", "struct B { a: A; }"),
+        ];
+        assert!(matches!(
+            CompilationUnit::new_merged(&source_files).compile_with_plugin("missing", None),
+            Err(CompileError::Plugin(PluginError::NotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn test_affected_source_files() {
+        let source_files = [
+            SourceFile::new_from_source("id.nproto", "alias Id = uint64;"),
+            SourceFile::new_from_source("player.nproto", "struct Player { id: Id; }"),
+            SourceFile::new_from_source("team.nproto", "struct Team { players: Player[]; }"),
+            SourceFile::new_from_source("item.nproto", "struct Item { name: string; }"),
+        ];
+        let unit = CompilationUnit::new_merged(&source_files);
+
+        let affected = unit
+            .affected_source_files(|source_file| source_file.path == "id.nproto")
+            .expect("failed to find affected files");
+        assert_eq!(
+            affected.into_iter().collect::<Vec<_>>(),
+            vec!["id.nproto", "player.nproto", "team.nproto"]
+        );
+
+        let affected = unit
+            .affected_source_files(|source_file| source_file.path == "item.nproto")
+            .expect("failed to find affected files");
+        assert_eq!(
+            affected.into_iter().collect::<Vec<_>>(),
+            vec!["item.nproto"]
+        );
     }
 }
@@ -0,0 +1,167 @@
+mod options;
+
+use crate::options::{MacroOptions, macro_codegen_options};
+use neatproto_compiler::{CompilationUnit, CompileError, SourceFile};
+use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, Span, TokenTree};
+use quote::quote;
+use std::path::PathBuf;
+use syn::parse_macro_input;
+
+/// Generates Rust code from a schema file and expands to the generated items.
+///
+/// The path is relative to the directory containing `Cargo.toml` of the crate being built.
+/// It can be followed by options, which have the same names as in `[target.rust]` section of
+/// the project config, e.g.:
+///
+/// ```ignore
+/// neatproto_macros::include_proto!("proto/game.nproto", with_serde = true);
+/// ```
+#[proc_macro]
+pub fn include_proto(input: TokenStream) -> TokenStream {
+    let MacroOptions { path, options } = parse_macro_input!(input as MacroOptions);
+
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = PathBuf::from(manifest_dir).join(path.value());
+    let full_path_str = full_path.display().to_string();
+
+    let source_file = match SourceFile::new_from_path(full_path) {
+        Ok(source_file) => source_file,
+        Err(error) => {
+            let message = CompileError::Io(path.value().into(), error).to_string();
+            return syn::Error::new(path.span(), message)
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    match CompilationUnit::new(&source_file).compile(&options) {
+        Ok(code) => match parse_generated_code(&code) {
+            // Makes the compiler rebuild the crate whenever the schema changes.
+            Ok(items) => quote! {
+                const _: &[u8] = include_bytes!(#full_path_str);
+                #items
+            }
+            .into(),
+            Err(error) => error.to_compile_error().into(),
+        },
+        Err(error) => syn::Error::new(path.span(), error.to_string())
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// Generates Rust code from a schema written inline and expands to the generated items.
+///
+/// ```
+/// neatproto_macros::neatproto! {
+///     struct Vector2 {
+///         x: float;
+///         y: float;
+///     }
+/// }
+///
+/// let vector = Vector2 { x: 1.0, y: 2.0 };
+/// ```
+#[proc_macro]
+pub fn neatproto(input: TokenStream) -> TokenStream {
+    let mut source = InlineSource::default();
+    source.write_tokens(proc_macro2::TokenStream::from(input));
+
+    let source_file = SourceFile::new_from_source("<inline>".to_string(), source.contents.clone());
+    let options = macro_codegen_options();
+
+    match CompilationUnit::new(&source_file).compile(&options) {
+        Ok(code) => match parse_generated_code(&code) {
+            Ok(items) => items.into(),
+            Err(error) => error.to_compile_error().into(),
+        },
+        Err(CompileError::Parse(error)) => {
+            // Report only the error itself, since the location is pointed to by the span.
+            let span = source.span_at(error.location.column);
+            syn::Error::new(span, format!("Parse error: {}", error.error))
+                .to_compile_error()
+                .into()
+        }
+        Err(error) => syn::Error::new(Span::call_site(), error.to_string())
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// Parses generated code into tokens. Fails only if the generator produced code which can not
+/// be tokenized, which is reported at the macro call site instead of panicking.
+fn parse_generated_code(code: &str) -> Result<proc_macro2::TokenStream, syn::Error> {
+    code.parse().map_err(|error| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("Rust code generator produced invalid tokens: {}", error),
+        )
+    })
+}
+
+/// Schema source reconstructed from macro input, written on a single line, so that parse
+/// error locations can be mapped back to spans of the original tokens.
+#[derive(Default)]
+struct InlineSource {
+    contents: String,
+    /// Columns (1-based, as reported by the parser) at which tokens start, with their spans.
+    token_spans: Vec<(usize, Span)>,
+}
+
+impl InlineSource {
+    fn write_tokens(&mut self, tokens: proc_macro2::TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.write_token(open, group.span_open());
+                    self.write_tokens(group.stream());
+                    self.write_token(close, group.span_close());
+                }
+                token => self.write_token(&token.to_string(), token.span()),
+            }
+        }
+    }
+
+    fn write_token(&mut self, token: &str, span: Span) {
+        if token.is_empty() {
+            return;
+        }
+        self.token_spans
+            .push((self.contents.chars().count() + 1, span));
+        self.contents.push_str(token);
+        self.contents.push(' ');
+    }
+
+    fn span_at(&self, column: usize) -> Span {
+        self.token_spans
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= column)
+            .map(|(_, span)| *span)
+            .unwrap_or_else(Span::call_site)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_generated_code;
+
+    #[test]
+    fn test_parse_generated_code() {
+        assert!(parse_generated_code("pub struct A { pub x: bool, }").is_ok());
+
+        let error = parse_generated_code("pub struct A {").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Rust code generator produced invalid tokens")
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use neatproto_codegen::{
+    CodeGenOptions, NameCase, RustCodeGenOptions, TaggedUnionRepr, TargetLanguage,
+};
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Lit, LitStr, Token};
+
+/// Arguments of `include_proto!`: a path, optionally followed by `name = value` options.
+pub struct MacroOptions {
+    pub path: LitStr,
+    pub options: CodeGenOptions,
+}
+
+impl Parse for MacroOptions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut options = macro_codegen_options();
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Lit = input.parse()?;
+            apply_option(&mut options, &name, &value)?;
+        }
+
+        Ok(Self { path, options })
+    }
+}
+
+/// Options used for code expanded by macros, which shares the module with other items and
+/// can not contain inner attributes.
+pub fn macro_codegen_options() -> CodeGenOptions {
+    CodeGenOptions {
+        target_language: TargetLanguage::Rust,
+        rust: RustCodeGenOptions {
+            serde_qualified_paths: true,
+            with_allow_lints: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn apply_option(options: &mut CodeGenOptions, name: &Ident, value: &Lit) -> syn::Result<()> {
+    match name.to_string().as_str() {
+        "field_name_case" => options.field_name_case = parse_name_case(value)?,
+        "type_name_case" => options.type_name_case = parse_name_case(value)?,
+        "enum_item_name_case" => options.enum_item_name_case = parse_name_case(value)?,
+        "tagged_union_repr" => options.tagged_union_repr = parse_tagged_union_repr(value)?,
+        "with_debug" => options.rust.with_debug = parse_bool(value)?,
+        "with_serde" => options.rust.with_serde = parse_bool(value)?,
+        "serde_struct_field_name_case" => {
+            options.rust.serde_struct_field_name_case = parse_name_case(value)?
+        }
+        "serde_enum_repr" => options.rust.serde_enum_repr = Some(parse_string(value)?),
+        "with_enum_display" => options.rust.with_enum_display = parse_bool(value)?,
+        "extra_derives" => {
+            options.rust.extra_derives = parse_string(value)?
+                .split(',')
+                .map(|derive| derive.trim().to_string())
+                .filter(|derive| !derive.is_empty())
+                .collect()
+        }
+        _ => return Err(syn::Error::new(name.span(), "unknown option")),
+    }
+    Ok(())
+}
+
+fn parse_bool(value: &Lit) -> syn::Result<bool> {
+    match value {
+        Lit::Bool(value) => Ok(value.value),
+        _ => Err(syn::Error::new(value.span(), "expected `true` or `false`")),
+    }
+}
+
+fn parse_string(value: &Lit) -> syn::Result<String> {
+    match value {
+        Lit::Str(value) => Ok(value.value()),
+        _ => Err(syn::Error::new(value.span(), "expected a string")),
+    }
+}
+
+fn parse_name_case(value: &Lit) -> syn::Result<NameCase> {
+    parse_string(value)?
+        .parse()
+        .map_err(|error: String| syn::Error::new(value.span(), error))
+}
+
+fn parse_tagged_union_repr(value: &Lit) -> syn::Result<TaggedUnionRepr> {
+    let name = parse_string(value)?;
+    TaggedUnionRepr::from_name(&name).ok_or_else(|| {
+        syn::Error::new(
+            value.span(),
+            format!("unknown tagged union representation '{}'", name),
+        )
+    })
+}
@@ -39,3 +39,11 @@ impl Display for LocalizedParseError {
         )
     }
 }
+
+impl std::error::Error for ParseError {}
+
+impl std::error::Error for LocalizedParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
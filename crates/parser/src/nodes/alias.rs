@@ -10,6 +10,8 @@ pub fn parse_alias(tokens: &mut Tokens) -> ParseResult<Alias> {
     Ok(Alias {
         alias_name: alias_name_token.value(),
         aliased_type_name: aliased_type_name_token.value(),
+        annotations: vec![],
+        properties: Default::default(),
     })
 }
 
@@ -0,0 +1,83 @@
+use crate::{LocalizedParseError, ParseError, ParseResult, Tokens};
+use neatproto_ast::{Annotation, Token};
+
+/// Parses an annotation, after its leading `@` was already consumed. Arguments in parentheses
+/// are optional, e.g. both `@foo` and `@foo("bar", 1)` are valid.
+pub fn parse_annotation(tokens: &mut Tokens) -> ParseResult<Annotation> {
+    let name_token = tokens.next_identifier()?;
+    let mut arguments = vec![];
+
+    if tokens
+        .peek()
+        .is_some_and(|token| token.token == Token::ParenOpen)
+    {
+        tokens.next();
+
+        loop {
+            let token = tokens.next_or_err()?;
+            match token.token {
+                Token::ParenClose => break,
+                Token::Identifier(value) | Token::Digit(value) | Token::String(value) => {
+                    arguments.push(value);
+
+                    let separator = tokens.next_or_err()?;
+                    match separator.token {
+                        Token::Comma => {}
+                        Token::ParenClose => break,
+                        _ => {
+                            return Err(LocalizedParseError {
+                                error: ParseError::UnexpectedToken(separator.token),
+                                location: separator.location,
+                            });
+                        }
+                    }
+                }
+                _ => {
+                    return Err(LocalizedParseError {
+                        error: ParseError::ExpectedLiteral,
+                        location: token.location,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(Annotation {
+        name: name_token.value(),
+        arguments,
+        location: name_token.location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_parser;
+    use rstest::rstest;
+
+    #[test]
+    fn test_annotation() {
+        let annotation = test_parser!(parse_annotation, "foo");
+        assert_eq!(&annotation.name, "foo");
+        assert!(annotation.arguments.is_empty());
+    }
+
+    #[test]
+    fn test_annotation_with_arguments() {
+        let annotation = test_parser!(parse_annotation, "foo(\"bar(baz)\", qux, 1,)");
+        assert_eq!(&annotation.name, "foo");
+        assert_eq!(annotation.arguments, vec!["bar(baz)", "qux", "1"]);
+    }
+
+    #[rstest]
+    #[should_panic(expected = "Expected an identifier in file 'test' at line 1:1")]
+    #[case("(")]
+    #[should_panic(expected = "Unexpected end of file in file 'test' at line 1:5")]
+    #[case("foo(")]
+    #[should_panic(expected = "Expected a literal in file 'test' at line 1:5")]
+    #[case("foo(;)")]
+    #[should_panic(expected = "Unexpected token 'b' in file 'test' at line 1:7")]
+    #[case("foo(a b)")]
+    fn test_invalid_annotation(#[case] code: &str) {
+        test_parser!(parse_annotation, code);
+    }
+}
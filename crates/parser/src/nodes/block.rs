@@ -1,5 +1,6 @@
 use crate::{
-    LocalizedParseError, ParseError, ParseResult, Tokens, parse_alias, parse_enum, parse_structure,
+    LocalizedParseError, ParseError, ParseResult, Tokens, parse_alias, parse_annotation,
+    parse_enum, parse_structure,
 };
 use neatproto_ast::{Block, BlockNode, LocalizedToken, Token};
 
@@ -15,6 +16,19 @@ pub fn parse_block(tokens: &mut Tokens) -> Result<Block, LocalizedParseError> {
 
 pub fn parse_block_node(tokens: &mut Tokens, token: LocalizedToken) -> ParseResult<BlockNode> {
     match &token.token {
+        Token::At => {
+            let annotation = parse_annotation(tokens)?;
+            let next_token = tokens.next_or_err()?;
+            let mut node = parse_block_node(tokens, next_token)?;
+            let annotations = match &mut node {
+                BlockNode::Structure(structure) => &mut structure.annotations,
+                BlockNode::Alias(alias) => &mut alias.annotations,
+                BlockNode::Enum(e) => &mut e.annotations,
+                BlockNode::Block(_) => unreachable!("blocks can not be annotated"),
+            };
+            annotations.insert(0, annotation);
+            Ok(node)
+        }
         Token::Identifier(value) => match value.as_str() {
             "struct" => parse_structure(tokens).map(BlockNode::Structure),
             "alias" => parse_alias(tokens).map(BlockNode::Alias),
@@ -86,4 +100,29 @@ mod tests {
     fn test_enum_eof() {
         test_parser!(parse_block, "enum");
     }
+
+    #[test]
+    fn test_annotations_in_block() {
+        let root_block = test_parser!(
+            parse_block,
+            "@first @second(\"a\", b) struct Foo { bar: float; }"
+        );
+        let Some(BlockNode::Structure(structure)) = root_block.nodes.first() else {
+            panic!("first node is not a structure");
+        };
+
+        let names = structure
+            .annotations
+            .iter()
+            .map(|annotation| annotation.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["first", "second"]);
+        assert_eq!(structure.annotations[1].arguments, vec!["a", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected end of file in file 'test' at line 1:5")]
+    fn test_annotation_eof() {
+        test_parser!(parse_block, "@foo");
+    }
 }
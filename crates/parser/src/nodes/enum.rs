@@ -44,6 +44,8 @@ pub fn parse_enum(tokens: &mut Tokens) -> ParseResult<Enum> {
                     last_item.structure = Some(Structure {
                         name: last_item.name.clone(),
                         fields: parse_structure_body(tokens)?,
+                        annotations: vec![],
+                        properties: Default::default(),
                     });
                 } else {
                     return Err(LocalizedParseError {
@@ -59,6 +61,8 @@ pub fn parse_enum(tokens: &mut Tokens) -> ParseResult<Enum> {
                 return Ok(Enum {
                     name: name_token.value(),
                     items,
+                    annotations: vec![],
+                    properties: Default::default(),
                 });
             }
             _ => {
@@ -87,7 +91,7 @@ mod tests {
         let e = test_parser!(parse_enum, "Foo { Bar = 1, Baz }");
         assert_eq!(&e.name, "Foo");
 
-        let item_bar = e.items.get(0).expect("item #0 was not found");
+        let item_bar = e.items.first().expect("item #0 was not found");
         assert_eq!(&item_bar.name, "Bar");
         assert_eq!(
             item_bar
@@ -124,7 +128,7 @@ mod tests {
             let fields = &item.structure.as_ref().unwrap().fields;
             assert_eq!(fields.len(), 2);
 
-            let field_a = fields.get(0).unwrap();
+            let field_a = fields.first().unwrap();
             assert_eq!(field_a.name, field_a_name);
             assert_eq!(
                 field_a.type_name.token.token,
@@ -156,7 +160,7 @@ mod tests {
         );
         assert_eq!(&e.name, "Foo");
 
-        test_tagged_union_item(e.items.get(0).unwrap(), "Bar", "a", "float", "b", "uint32");
+        test_tagged_union_item(e.items.first().unwrap(), "Bar", "a", "float", "b", "uint32");
         test_tagged_union_item(e.items.get(1).unwrap(), "Baz", "a", "uint32", "b", "float");
     }
 
@@ -1,9 +1,11 @@
 mod alias;
+mod annotation;
 mod block;
 mod structure;
 mod r#enum;
 
 pub use alias::*;
+pub use annotation::*;
 pub use block::*;
 pub use structure::*;
 pub use r#enum::*;
\ No newline at end of file
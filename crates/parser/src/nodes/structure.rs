@@ -7,6 +7,8 @@ pub fn parse_structure(tokens: &mut Tokens) -> ParseResult<Structure> {
     Ok(Structure {
         name: name_token.value(),
         fields: parse_structure_body(tokens)?,
+        annotations: vec![],
+        properties: Default::default(),
     })
 }
 
@@ -100,7 +102,7 @@ mod tests {
         );
         assert_eq!(&structure.name, "Foo");
 
-        let field_bar = structure.fields.get(0).expect("field #0 was not found");
+        let field_bar = structure.fields.first().expect("field #0 was not found");
         assert_eq!(&field_bar.name, "bar");
         assert_eq!(&field_bar.type_name.token.value(), "float");
 
@@ -111,7 +113,7 @@ mod tests {
         let field_fixed_bars = structure.fields.get(2).expect("field #2 was not found");
         assert_eq!(&field_fixed_bars.name, "fixedBars");
         assert_eq!(&field_fixed_bars.type_name.token.value(), "float");
-        assert_eq!(field_fixed_bars.type_name.is_array, true);
+        assert!(field_fixed_bars.type_name.is_array);
         assert_eq!(
             field_fixed_bars
                 .type_name
@@ -125,7 +127,7 @@ mod tests {
         let field_dynamic_bars = structure.fields.get(3).expect("field #3 was not found");
         assert_eq!(&field_dynamic_bars.name, "dynamicBars");
         assert_eq!(&field_dynamic_bars.type_name.token.value(), "float");
-        assert_eq!(field_dynamic_bars.type_name.is_array, true);
+        assert!(field_dynamic_bars.type_name.is_array);
         assert!(field_dynamic_bars.type_name.array_size.is_none());
     }
 
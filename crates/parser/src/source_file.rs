@@ -21,7 +21,7 @@ impl SourceFile {
         }
     }
 
-    pub fn tokens(&self) -> Tokens {
+    pub fn tokens(&self) -> Tokens<'_> {
         Tokens::new(self)
     }
 }
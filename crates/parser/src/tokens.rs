@@ -3,6 +3,7 @@ use crate::{LocalizedParseError, ParseError, ParseResult};
 use neatproto_ast::{LocalizedToken, SourceLocation, Token};
 use std::str::Chars;
 
+#[derive(Clone)]
 pub struct Tokens<'a> {
     _source_file: &'a SourceFile,
     chars: Chars<'a>,
@@ -22,6 +23,11 @@ impl<'a> Tokens<'a> {
         }
     }
 
+    /// Returns the next token without consuming it.
+    pub fn peek(&self) -> Option<LocalizedToken> {
+        self.clone().next()
+    }
+
     pub fn next_or_err(&mut self) -> ParseResult<LocalizedToken> {
         self.next().ok_or_else(|| LocalizedParseError {
             error: ParseError::UnexpectedEndOfFile,
@@ -70,8 +76,7 @@ impl<'a> Tokens<'a> {
         let mut value = String::with_capacity(16);
         value.push(c);
 
-        let mut it = self.chars.clone();
-        while let Some(c) = it.next() {
+        for c in self.chars.clone() {
             if !c.is_alphanumeric() && c != '_' {
                 break;
             }
@@ -84,16 +89,15 @@ impl<'a> Tokens<'a> {
     }
 
     fn read_digit(&mut self, c: char) -> Option<Token> {
-        if !c.is_digit(10) {
+        if !c.is_ascii_digit() {
             return None;
         }
 
         let mut value = String::with_capacity(8);
         value.push(c);
 
-        let mut it = self.chars.clone();
-        while let Some(c) = it.next() {
-            if !c.is_digit(10) {
+        for c in self.chars.clone() {
+            if !c.is_ascii_digit() {
                 break;
             }
 
@@ -111,7 +115,7 @@ impl<'a> Tokens<'a> {
 
         let mut value = String::with_capacity(16);
 
-        while let Some(c) = self.chars.next() {
+        for c in self.chars.by_ref() {
             if c == '"' {
                 break;
             }
@@ -180,3 +184,23 @@ impl<'a> Iterator for Tokens<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::SourceFile;
+    use neatproto_ast::Token;
+
+    #[test]
+    fn test_string_token_location() {
+        let source_file = SourceFile::new_from_source("test", "(\"ab\", c)");
+        let tokens = source_file.tokens().collect::<Vec<_>>();
+
+        assert_eq!(tokens[1].token, Token::String("ab".to_string()));
+        assert_eq!(tokens[1].location.column, 2);
+
+        // Quotes are a part of the string token, so the following tokens start after them.
+        assert_eq!(tokens[2].token, Token::Comma);
+        assert_eq!(tokens[2].location.column, 6);
+        assert_eq!(tokens[3].location.column, 8);
+    }
+}